@@ -0,0 +1,17 @@
+//! Ftd uuids of every component kind this crate emits
+//!
+//! These are in ftd's notation, see [`ftd_uuid_to_uuid`](crate::ftd_data::ftd_uuid_to_uuid).
+//! A new component kind should add its uuid here, to the uuid test in the breadboard module, and to its cost table.
+
+use uuid::{Uuid, uuid};
+
+pub(super) const CONSTANT_UUID: Uuid = uuid!("9142c70d-7833-41cd-804d-554e990b6904");
+pub(super) const RANDOM_INPUT_UUID: Uuid = uuid!("268b7db2-bccf-41fd-8cfa-3f21d2f2bacb");
+pub(super) const ALTITUDE_UUID: Uuid = uuid!("ae46572b-dff8-4153-97dc-146108f3a64f");
+pub(super) const POSITION_UUID: Uuid = uuid!("e20d6a3a-c0b9-4665-8749-7a85c40afabe");
+/// Speed and velocity are the same ftd component, velocity is just the modes which output a vector
+pub(super) const SPEED_UUID: Uuid = uuid!("c8f64443-b81f-4b75-8105-18cd6e453539");
+pub(super) const TARGET_INFO_UUID: Uuid = uuid!("5390bcf0-d09d-40b8-99a3-8d3752e656c6");
+pub(super) const MULTIPLY_UUID: Uuid = uuid!("930e5331-cecf-408a-8d90-dac6b479d5b0");
+pub(super) const SWITCH_UUID: Uuid = uuid!("581de01e-3754-45f6-9133-f51443844eca");
+pub(super) const EVALUATOR_UUID: Uuid = uuid!("7cf3b706-757e-428a-bb45-454a17ed710a");
@@ -0,0 +1,236 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use uuid::Uuid;
+
+use crate::ftd_data::DataEntryId;
+use super::{LineInner, MAX_COMPONENTS, MAX_EVALUATOR_EXPRESSION_LENGTH, MAX_MULTIPLY_INPUTS};
+use super::parse::ParseError;
+
+/// Errors which can occur while building a breadboard
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BreadboardError {
+    /// A line from a different breadboard was used as an input
+    ForeignLine,
+    /// The breadboard has more than [`MAX_COMPONENTS`] components
+    TooManyComponents {
+        count: usize,
+    },
+    /// The expression string of the evaluator at `component_index` is longer than [`MAX_EVALUATOR_EXPRESSION_LENGTH`]
+    ExpressionTooLong {
+        component_index: usize,
+        length: usize,
+    },
+    /// A windowed component was given a window longer than it supports
+    WindowTooLarge {
+        window: usize,
+        max: usize,
+    },
+    /// A component was added while the breadboard's components were borrowed
+    ///
+    /// Breadboard methods never call user code while they borrow the components, so this is a bug in this crate.
+    ComponentsBorrowed,
+}
+
+impl Display for BreadboardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ForeignLine => write!(f, "line from a different breadboard passed into breadboard"),
+            Self::TooManyComponents { count } => {
+                write!(f, "breadboard has {count} components, but at most {MAX_COMPONENTS} can be saved")
+            },
+            Self::ExpressionTooLong { component_index, length } => write!(
+                f,
+                "evaluator {component_index} has an expression string of {length} bytes, but at most {MAX_EVALUATOR_EXPRESSION_LENGTH} can be saved",
+            ),
+            Self::WindowTooLarge { window, max } => {
+                write!(f, "window of {window} frames is too large, at most {max} frames are supported")
+            },
+            Self::ComponentsBorrowed => write!(f, "component inserted while breadboard components are borrowed"),
+        }
+    }
+}
+
+impl Error for BreadboardError {}
+
+/// Errors which can occur while rebuilding a breadboard from blueprint data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportError {
+    /// No block in the blueprint contains a breadboard
+    NoBreadboard,
+    /// An entry of the breadboard's component list is missing or invalid
+    InvalidComponentList {
+        entry_id: DataEntryId,
+    },
+    /// The section holding a component's data is missing
+    MissingComponentSection {
+        component_index: usize,
+    },
+    /// The component is not one of the kinds this crate can emit
+    UnsupportedComponent {
+        component_index: usize,
+        ftd_uuid: Uuid,
+    },
+    /// An entry of a component is missing, has the wrong type, or has a value this crate does not support
+    InvalidEntry {
+        component_index: usize,
+        entry_id: DataEntryId,
+    },
+    /// The component has the wrong number of inputs for its kind
+    WrongInputCount {
+        component_index: usize,
+        count: usize,
+    },
+    /// An input of the component is connected to an output which is not in the breadboard
+    UnknownWire {
+        component_index: usize,
+    },
+    /// The expression string of an evaluator could not be parsed
+    InvalidExpression {
+        component_index: usize,
+        error: ParseError,
+    },
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoBreadboard => write!(f, "blueprint does not contain a breadboard"),
+            Self::InvalidComponentList { entry_id } => write!(f, "invalid entry {entry_id} in breadboard component list"),
+            Self::MissingComponentSection { component_index } => {
+                write!(f, "section data for component {component_index} is missing")
+            },
+            Self::UnsupportedComponent { component_index, ftd_uuid } => {
+                write!(f, "component {component_index} has unsupported kind {ftd_uuid}")
+            },
+            Self::InvalidEntry { component_index, entry_id } => {
+                write!(f, "entry {entry_id} of component {component_index} is missing or invalid")
+            },
+            Self::WrongInputCount { component_index, count } => {
+                write!(f, "component {component_index} can not have {count} inputs")
+            },
+            Self::UnknownWire { component_index } => {
+                write!(f, "input of component {component_index} is connected to an unknown output")
+            },
+            Self::InvalidExpression { component_index, error } => {
+                write!(f, "could not parse expression of evaluator component {component_index}: {error}")
+            },
+        }
+    }
+}
+
+impl Error for ImportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::InvalidExpression { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Errors which can occur while running a [`Simulation`](super::Simulation)
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimulationError {
+    /// A component reads a sensor output which was not given a value
+    MissingSensorValue {
+        component_index: usize,
+        line: LineInner,
+    },
+    /// An input of the component, or an operand of one of its expressions, has a type it does not accept
+    InvalidValues {
+        component_index: usize,
+        message: String,
+    },
+}
+
+impl Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingSensorValue { component_index, line } => write!(
+                f,
+                "component {component_index} reads output {} of sensor {}, which has no value set",
+                line.output_index,
+                line.component_index,
+            ),
+            Self::InvalidValues { component_index, message } => write!(f, "component {component_index}: {message}"),
+        }
+    }
+}
+
+impl Error for SimulationError {}
+
+/// Problems found by [`Breadboard::validate`](super::Breadboard::validate)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// The breadboard can't be saved, for problems which are not caused by a single component
+    Breadboard(BreadboardError),
+    /// The component at `component_index` can't be saved
+    Component {
+        component_index: usize,
+        error: BreadboardError,
+    },
+    /// Input `input_index` of the component is wired to a component or output which does not exist
+    InvalidInput {
+        component_index: usize,
+        input_index: usize,
+        line: LineInner,
+    },
+    /// The component is not needed for any marked output, this is only a warning
+    UnusedComponent {
+        component_index: usize,
+    },
+    /// The multiply component has more than [`MAX_MULTIPLY_INPUTS`] inputs, this is only a warning
+    ManyMultiplyInputs {
+        component_index: usize,
+        count: usize,
+    },
+}
+
+impl ValidationError {
+    /// Returns true if this does not stop the breadboard from being saved
+    pub fn is_warning(&self) -> bool {
+        matches!(self, Self::UnusedComponent { .. } | Self::ManyMultiplyInputs { .. })
+    }
+
+    /// Index of the component this is about, if it is about a single component
+    pub fn component_index(&self) -> Option<usize> {
+        match self {
+            Self::Breadboard(_) => None,
+            Self::Component { component_index, .. }
+            | Self::InvalidInput { component_index, .. }
+            | Self::UnusedComponent { component_index }
+            | Self::ManyMultiplyInputs { component_index, .. } => Some(*component_index),
+        }
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Breadboard(error) => write!(f, "{error}"),
+            Self::Component { component_index, error } => write!(f, "component {component_index}: {error}"),
+            Self::InvalidInput { component_index, input_index, line } => write!(
+                f,
+                "input {input_index} of component {component_index} is wired to output {} of component {}, which does not exist",
+                line.output_index,
+                line.component_index,
+            ),
+            Self::UnusedComponent { component_index } => {
+                write!(f, "component {component_index} is not needed for any marked output")
+            },
+            Self::ManyMultiplyInputs { component_index, count } => write!(
+                f,
+                "multiply component {component_index} has {count} inputs, more than the {MAX_MULTIPLY_INPUTS} which have been tried in ftd",
+            ),
+        }
+    }
+}
+
+impl Error for ValidationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Breadboard(error) | Self::Component { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
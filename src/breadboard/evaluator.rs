@@ -1,11 +1,14 @@
 use core::fmt::{self, Display, Write};
+use core::ops::{Add, Sub, Mul, Div, Rem, Neg, Not};
 
-use uuid::{Uuid, uuid};
+use uuid::Uuid;
 
 use crate::ftd_data::{SectionData, DataEntry};
-use super::{BNumber, BQuaternion, BString, BVector3, Breadboard, Component, Line, LineInner, LineValue};
+use super::{BNumber, BQuaternion, BString, BVector3, Breadboard, BreadboardError, Component, Line, LineInner, LineValue, SimValue, MAX_EVALUATOR_EXPRESSION_LENGTH};
+use super::component_uuid::EVALUATOR_UUID;
+use super::parse::{parse_expression_list, ParseError};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Evaluator {
     inputs: Vec<LineInner>,
     // there is one expression for each output
@@ -25,25 +28,45 @@ impl Evaluator {
         self.inputs.push(input_line);
         Some(expr)
     }
-}
 
-impl Component for Evaluator {
-    fn ftd_uuid(&self) -> Uuid {
-        uuid!("7cf3b706-757e-428a-bb45-454a17ed710a")
+    /// Adds a new output to the evaluator, outputs are numbered in the order they are added
+    pub fn add_output(&mut self, expr: EvaluatorExpression) {
+        // builder methods only pass lines of the right types, so this catches mistakes in the expressions they build
+        debug_assert!(expr.value_type().is_ok(), "invalid evaluator expression: {}", expr.value_type().unwrap_err());
+        self.exprs.push(expr);
     }
 
-    fn section_data(&self) -> SectionData {
+    /// The comma seperated expression string this evaluator is saved with
+    fn expression_string(&self) -> String {
         let mut expr_string = String::new();
         for (i, expr) in self.exprs.iter().enumerate() {
             if i != 0 {
-                expr_string.push_str(",");
+                expr_string.push(',');
             }
 
             write!(expr_string, "{expr}").unwrap();
         }
 
+        expr_string
+    }
+
+    /// Rebuilds an evaluator from its inputs and the comma seperated expression string it was saved with
+    pub(super) fn from_saved(inputs: Vec<LineInner>, expr_string: &str) -> Result<Self, ParseError> {
+        Ok(Evaluator {
+            inputs,
+            exprs: parse_expression_list(expr_string)?,
+        })
+    }
+}
+
+impl Component for Evaluator {
+    fn ftd_uuid(&self) -> Uuid {
+        EVALUATOR_UUID
+    }
+
+    fn section_data(&self) -> SectionData {
         SectionData::default()
-            .with_entry(0, DataEntry::String(expr_string))
+            .with_entry(0, DataEntry::String(self.expression_string()))
     }
 
     fn num_outputs(&self) -> usize {
@@ -53,6 +76,28 @@ impl Component for Evaluator {
     fn inputs(&self) -> &[LineInner] {
         self.inputs.as_slice()
     }
+
+    fn inputs_mut(&mut self) -> &mut [LineInner] {
+        self.inputs.as_mut_slice()
+    }
+
+    fn simulate(&self, inputs: &[SimValue], previous_outputs: &[SimValue]) -> Option<Result<Vec<SimValue>, String>> {
+        Some(self.exprs.iter()
+            .map(|expr| expr.evaluate(inputs, previous_outputs))
+            .collect())
+    }
+
+    fn check_saveable(&self, component_index: usize) -> Result<(), BreadboardError> {
+        let length = self.expression_string().len();
+        if length > MAX_EVALUATOR_EXPRESSION_LENGTH {
+            Err(BreadboardError::ExpressionTooLong {
+                component_index,
+                length,
+            })
+        } else {
+            Ok(())
+        }
+    }
 }
 
 macro_rules! make_bb_method {
@@ -105,7 +150,7 @@ macro_rules! make_bb_method_named {
 }
 
 impl Breadboard {
-    fn evaluator_expr<T: LineValue + ?Sized>(
+    pub(super) fn evaluator_expr<T: LineValue + ?Sized>(
         &self,
         val1: Line<impl LineValue + ?Sized>,
         expr_fn: impl FnOnce(Box<EvaluatorExpression>) -> EvaluatorExpression,
@@ -120,13 +165,14 @@ impl Breadboard {
         self.insert_component_with_output(eval)
     }
 
-    fn evaluator_expr2<T: LineValue + ?Sized>(
+    pub(super) fn evaluator_expr2<T: LineValue + ?Sized>(
         &self,
         val1: Line<impl LineValue + ?Sized>,
         val2: Line<impl LineValue + ?Sized>,
         expr_fn: impl FnOnce(Box<EvaluatorExpression>, Box<EvaluatorExpression>) -> EvaluatorExpression,
     ) -> Line<T> {
         self.verify_line(&val1);
+        self.verify_line(&val2);
 
         let mut eval = Evaluator::default();
 
@@ -137,7 +183,7 @@ impl Breadboard {
         self.insert_component_with_output(eval)
     }
 
-    fn evaluator_expr3<T: LineValue + ?Sized>(
+    pub(super) fn evaluator_expr3<T: LineValue + ?Sized>(
         &self,
         val1: Line<impl LineValue + ?Sized>,
         val2: Line<impl LineValue + ?Sized>,
@@ -145,6 +191,8 @@ impl Breadboard {
         expr_fn: impl FnOnce(Box<EvaluatorExpression>, Box<EvaluatorExpression>, Box<EvaluatorExpression>) -> EvaluatorExpression,
     ) -> Line<T> {
         self.verify_line(&val1);
+        self.verify_line(&val2);
+        self.verify_line(&val3);
 
         let mut eval = Evaluator::default();
 
@@ -156,6 +204,29 @@ impl Breadboard {
         self.insert_component_with_output(eval)
     }
 
+    /// Adds all the lines as inputs of the evaluator, and returns the expressions for each of them
+    pub(super) fn evaluator_inputs<T: LineValue + ?Sized, const N: usize>(
+        &self,
+        eval: &mut Evaluator,
+        lines: [&Line<T>; N],
+    ) -> [EvaluatorExpression; N] {
+        lines.map(|line| {
+            self.verify_line(line);
+            eval.get_input(line.inner).expect("too many inputs for evaluator")
+        })
+    }
+
+    /// Constant number with full f64 precision
+    ///
+    /// The constant component stores its value as an f32 entry, this instead writes the value
+    /// into an evaluator expression so no precision is lost when the blueprint is saved.
+    pub fn precise_constant(&self, n: f64) -> Line<BNumber> {
+        let mut eval = Evaluator::default();
+        eval.add_output(EvaluatorExpression::Float(n));
+
+        self.insert_component_with_output(eval)
+    }
+
     pub fn new_vector(&self, x: f64, y: f64, z: f64) -> Line<BVector3> {
         let expr = EvaluatorExpression::Vector(
             Box::new(EvaluatorExpression::Float(x)),
@@ -194,6 +265,13 @@ impl Breadboard {
     make_bb_method!(min3, Min3, a: BNumber, b: BNumber, c: BNumber, BNumber);
     make_bb_method!(minv, MinV, vec: BVector3, BNumber);
 
+    /// Limits `num` to the range `[min, max]`
+    ///
+    /// If `min` is above `max`, `max` is output.
+    pub fn clamp(&self, num: Line<BNumber>, min: Line<BNumber>, max: Line<BNumber>) -> Line<BNumber> {
+        self.evaluator_expr3(num, min, max, |num, min, max| EvaluatorExpression::clamp(*num, *min, *max))
+    }
+
     pub fn b_if<T: LineValue + ?Sized>(&self, condition: Line<BNumber>, true_value: Line<T>, false_value: Line<T>) -> Line<T> {
         self.evaluator_expr3(condition, true_value, false_value, |a, b, c| {
             EvaluatorExpression::If {
@@ -204,6 +282,73 @@ impl Breadboard {
         })
     }
 
+    /// Like [`b_if`](Self::b_if), but returns an error instead of panicking if any line is from a different breadboard
+    pub fn try_b_if<T: LineValue + ?Sized>(
+        &self,
+        condition: Line<BNumber>,
+        true_value: Line<T>,
+        false_value: Line<T>,
+    ) -> Result<Line<T>, BreadboardError> {
+        self.try_verify_line(&condition)?;
+        self.try_verify_line(&true_value)?;
+        self.try_verify_line(&false_value)?;
+        self.try_verify_insertable()?;
+
+        Ok(self.b_if(condition, true_value, false_value))
+    }
+
+    /// Three way version of [`switch`](Self::switch) which outputs a fixed value when `switch_signal` is low or high
+    ///
+    /// Outputs `low_value` when `switch_signal <= low_threshold`, `high_value` when `switch_signal > high_threshold`,
+    /// and `passthrough` otherwise. The low check is done first, so if `high_threshold` is below `low_threshold`
+    /// passthrough is never output. The boundaries match [`switch`](Self::switch), which outputs passthrough
+    /// only when the signal is strictly above its threshold.
+    pub fn multi_switch(
+        &self,
+        passthrough: Line<BNumber>,
+        switch_signal: Line<BNumber>,
+        low_threshold: f32,
+        high_threshold: f32,
+        low_value: f32,
+        high_value: f32,
+    ) -> Line<BNumber> {
+        self.evaluator_expr2(passthrough, switch_signal, |passthrough, switch_signal| {
+            EvaluatorExpression::if_else(
+                EvaluatorExpression::Lte(switch_signal.clone(), Box::new(EvaluatorExpression::Float(low_threshold.into()))),
+                EvaluatorExpression::Float(low_value.into()),
+                EvaluatorExpression::if_else(
+                    EvaluatorExpression::Gt(switch_signal, Box::new(EvaluatorExpression::Float(high_threshold.into()))),
+                    EvaluatorExpression::Float(high_value.into()),
+                    *passthrough,
+                ),
+            )
+        })
+    }
+
+    /// Outputs the entry of `table` at `index` rounded to the nearest whole number, with halves rounded up
+    ///
+    /// Indices below 0 output the first entry and indices past the end output the last entry.
+    /// An empty table always outputs 0.
+    pub fn lookup(&self, index: Line<BNumber>, table: &[f32]) -> Line<BNumber> {
+        let Some((last, rest)) = table.split_last() else {
+            return self.constant(0.0);
+        };
+
+        self.evaluator_expr(index, |index| {
+            // build from the last entry outwards so lower indices are checked first
+            rest.iter()
+                .enumerate()
+                .rev()
+                .fold(EvaluatorExpression::Float((*last).into()), |higher_entries, (i, value)| {
+                    EvaluatorExpression::if_else(
+                        EvaluatorExpression::Lt(index.clone(), Box::new(EvaluatorExpression::Float(i as f64 + 0.5))),
+                        EvaluatorExpression::Float((*value).into()),
+                        higher_entries,
+                    )
+                })
+        })
+    }
+
     make_bb_method!(vector, Vector, x: BNumber, y: BNumber, z: BNumber, BVector3);
     make_bb_method_named!(new_rotation_between, MakeRotationBetween, from_vector: BVector3, to_vector: BVector3, BQuaternion);
     make_bb_method_named!(rotation_from_euler_angles, FromEuler, pitch: BNumber, yaw: BNumber, roll: BNumber, BQuaternion);
@@ -225,6 +370,35 @@ impl Breadboard {
     make_bb_method!(square_magnitude, SquareMagnitude, vector: BVector3, BNumber);
     make_bb_method!(rotation_inverse, RotationInverse, rotation: BQuaternion, BQuaternion);
 
+    // like unity, +z is forward, +y is up and +x is right
+
+    /// Forward direction of `rotation`, which is the z axis rotated by it
+    pub fn rotation_forward(&self, rotation: Line<BQuaternion>) -> Line<BVector3> {
+        self.rotated_axis(rotation, 0, 0, 1)
+    }
+
+    /// Up direction of `rotation`, which is the y axis rotated by it
+    pub fn rotation_up(&self, rotation: Line<BQuaternion>) -> Line<BVector3> {
+        self.rotated_axis(rotation, 0, 1, 0)
+    }
+
+    /// Right direction of `rotation`, which is the x axis rotated by it
+    pub fn rotation_right(&self, rotation: Line<BQuaternion>) -> Line<BVector3> {
+        self.rotated_axis(rotation, 1, 0, 0)
+    }
+
+    fn rotated_axis(&self, rotation: Line<BQuaternion>, x: i64, y: i64, z: i64) -> Line<BVector3> {
+        self.evaluator_expr(rotation, |rotation| {
+            let axis = EvaluatorExpression::Vector(
+                Box::new(EvaluatorExpression::Int(x)),
+                Box::new(EvaluatorExpression::Int(y)),
+                Box::new(EvaluatorExpression::Int(z)),
+            );
+
+            axis * *rotation
+        })
+    }
+
     // operators
     make_bb_method!(add, Add, a: BNumber, b: BNumber, BNumber);
     make_bb_method!(addv, Add, a: BVector3, b: BVector3, BVector3);
@@ -264,7 +438,7 @@ impl Breadboard {
     make_bb_method!(negate, Negate, n: BNumber, BNumber);
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum EvaluatorExpression {
     InputA,
     InputB,
@@ -272,6 +446,10 @@ pub enum EvaluatorExpression {
     InputD,
     InputE,
     Int(i64),
+    /// Number literal, which is not clamped
+    ///
+    /// Infinity and nan can't be written in an expression, so infinities are written as the largest
+    /// finite f32 with the same sign and nan is written as 0. The simulation uses the written value.
     Float(f64),
     Sin(Box<Self>),
     Cos(Box<Self>),
@@ -353,8 +531,9 @@ pub enum EvaluatorExpression {
     Sub(Box<Self>,Box<Self>),
     // vector cross product
     Cross(Box<Self>, Box<Self>),
-    // works for 2 numbers, 2 vectors (dot product), rotation then vector (rotate vector),
+    // works for 2 numbers, 2 vectors (dot product), vector then rotation (rotate vector),
     // number then vector, vector then number, 2 rotations (new rotation where lhs rotate after rhs)
+    // vectors are always written before the rotation, like in rotate and vector / rotation below
     Mul(Box<Self>, Box<Self>),
     // works for 2 numbers, vector / number, vector / rotation (apply inverse rotation)
     Div(Box<Self>, Box<Self>),
@@ -384,6 +563,93 @@ impl EvaluatorExpression {
             _ => None,
         }
     }
+
+    /// Number output of this evaluator at index `n` from the previous frame
+    pub(super) fn previous_output(n: i64) -> Self {
+        Self::Output(Box::new(Self::Int(n)))
+    }
+
+    /// Vector output of this evaluator at index `n` from the previous frame
+    pub(super) fn previous_output_vector(n: i64) -> Self {
+        Self::OutputV(Box::new(Self::Int(n)))
+    }
+
+    pub(super) fn if_else(condition: Self, true_value: Self, false_value: Self) -> Self {
+        Self::If {
+            condition: Box::new(condition),
+            true_value: Box::new(true_value),
+            false_value: Box::new(false_value),
+        }
+    }
+
+    pub(super) fn min(a: Self, b: Self) -> Self {
+        Self::Min2(Box::new(a), Box::new(b))
+    }
+
+    pub(super) fn max(a: Self, b: Self) -> Self {
+        Self::Max2(Box::new(a), Box::new(b))
+    }
+
+    pub(super) fn clamp(val: Self, min: Self, max: Self) -> Self {
+        Self::min(Self::max(val, min), max)
+    }
+}
+
+/// Value a [`EvaluatorExpression::Float`] literal is written as
+pub(super) fn finite_literal(val: f64) -> f64 {
+    if val.is_nan() {
+        0.0
+    } else if val.is_infinite() {
+        val.signum() * f64::from(f32::MAX)
+    } else {
+        val
+    }
+}
+
+impl From<f64> for EvaluatorExpression {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<i64> for EvaluatorExpression {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+macro_rules! expr_op {
+    ($trait:ident, $method:ident, $variant:ident) => {
+        impl $trait for EvaluatorExpression {
+            type Output = Self;
+
+            fn $method(self, rhs: Self) -> Self {
+                Self::$variant(Box::new(self), Box::new(rhs))
+            }
+        }
+    };
+}
+
+expr_op!(Add, add, Add);
+expr_op!(Sub, sub, Sub);
+expr_op!(Mul, mul, Mul);
+expr_op!(Div, div, Div);
+expr_op!(Rem, rem, Mod);
+
+impl Neg for EvaluatorExpression {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::Negate(Box::new(self))
+    }
+}
+
+impl Not for EvaluatorExpression {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self::Not(Box::new(self))
+    }
 }
 
 impl Display for EvaluatorExpression {
@@ -395,7 +661,15 @@ impl Display for EvaluatorExpression {
             Self::InputD => write!(f, "d"),
             Self::InputE => write!(f, "e"),
             Self::Int(val) => write!(f, "{val}"),
-            Self::Float(val) => write!(f, "{val}"),
+            // integral floats are written with a decimal point so they are not read back as ints
+            Self::Float(val) => {
+                let val = finite_literal(*val);
+                if val.fract() == 0.0 {
+                    write!(f, "{val}.0")
+                } else {
+                    write!(f, "{val}")
+                }
+            },
             Self::Sin(val) => write!(f, "Sin({val})"),
             Self::Cos(val) => write!(f, "Cos({val})"),
             Self::Tan(val) => write!(f, "Tan({val})"),
@@ -427,7 +701,7 @@ impl Display for EvaluatorExpression {
             Self::MakeRotationBetween {
                 from_vector,
                 to_vector,
-            } => write!(f, "FromToRot({from_vector}, {to_vector}"),
+            } => write!(f, "FromToRot({from_vector}, {to_vector})"),
             Self::FromEuler {
                 pitch,
                 yaw,
@@ -448,11 +722,11 @@ impl Display for EvaluatorExpression {
             Self::SetY {
                 vector,
                 y,
-            } => write!(f, "setX({vector}, {y})"),
+            } => write!(f, "setY({vector}, {y})"),
             Self::SetZ {
                 vector,
                 z,
-            } => write!(f, "setX({vector}, {z})"),
+            } => write!(f, "setZ({vector}, {z})"),
             Self::OutputV(val) => write!(f, "outputV({val})"),
             Self::Output(val) => write!(f, "output({val})"),
             Self::GetX(val) => write!(f, "({val}).x"),
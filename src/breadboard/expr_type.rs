@@ -0,0 +1,218 @@
+//! Checking that the operands of evaluator expressions have types ftd accepts
+//!
+//! Evaluator inputs are untyped wires, so their types are unknown and accepted anywhere.
+//! Only expressions where the known types can't work are rejected, such as `.magnitude` of a number,
+//! so a checked expression can still fail in ftd if an input has the wrong type.
+
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use super::evaluator::EvaluatorExpression;
+
+/// Type of a value in an evaluator expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    Number,
+    Vector,
+    Quaternion,
+    String,
+}
+
+/// Error returned when the operands of an evaluator expression have types it does not accept
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpressionTypeError {
+    /// The expression which does not accept its operands
+    expression: String,
+    /// Known types of the operands, in order
+    operand_types: Vec<Option<ValueType>>,
+}
+
+impl ExpressionTypeError {
+    fn new(expression: &EvaluatorExpression, operand_types: &[Option<ValueType>]) -> Self {
+        ExpressionTypeError {
+            expression: expression.to_string(),
+            operand_types: operand_types.to_vec(),
+        }
+    }
+}
+
+impl Display for ExpressionTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` does not accept operands of type ", self.expression)?;
+
+        for (i, operand_type) in self.operand_types.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+
+            match operand_type {
+                Some(operand_type) => write!(f, "{operand_type:?}")?,
+                None => write!(f, "unknown")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for ExpressionTypeError {}
+
+/// Returns the output type for the operand types, using the first signature which matches
+///
+/// An unknown operand matches any type. If several signatures with different outputs match, the output is unknown.
+fn match_signature(
+    expr: &EvaluatorExpression,
+    operand_types: &[Option<ValueType>],
+    signatures: &[(&[ValueType], ValueType)],
+) -> Result<Option<ValueType>, ExpressionTypeError> {
+    let mut output = None;
+    let mut matched = false;
+
+    for (params, signature_output) in signatures {
+        let matches = params.iter()
+            .zip(operand_types)
+            .all(|(param, operand)| operand.is_none_or(|operand| operand == *param));
+        if !matches {
+            continue;
+        }
+
+        if !matched {
+            output = Some(*signature_output);
+            matched = true;
+        } else if output != Some(*signature_output) {
+            output = None;
+        }
+    }
+
+    if matched {
+        Ok(output)
+    } else {
+        Err(ExpressionTypeError::new(expr, operand_types))
+    }
+}
+
+impl EvaluatorExpression {
+    /// Returns the type of the expression's value, or `None` if it depends on the types of inputs
+    ///
+    /// This returns an error if any part of the expression gets operands of types it does not accept,
+    /// see the [module docs](self) for what can be checked.
+    ///
+    /// ```
+    /// use bakery::prelude::*;
+    ///
+    /// let scalar_magnitude = EvaluatorExpression::Magnitude(Box::new(EvaluatorExpression::Float(2.0)));
+    /// assert!(scalar_magnitude.value_type().is_err());
+    ///
+    /// let input_magnitude = EvaluatorExpression::Magnitude(Box::new(EvaluatorExpression::InputA));
+    /// assert_eq!(input_magnitude.value_type(), Ok(Some(ValueType::Number)));
+    /// ```
+    pub fn value_type(&self) -> Result<Option<ValueType>, ExpressionTypeError> {
+        use ValueType::{Number as N, Quaternion as Q, String as S, Vector as V};
+
+        let operand_types = |operands: &[&Self]| {
+            operands.iter()
+                .map(|operand| operand.value_type())
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let check = |operands: &[&Self], signatures: &[(&[ValueType], ValueType)]| {
+            match_signature(self, &operand_types(operands)?, signatures)
+        };
+
+        match self {
+            Self::InputA | Self::InputB | Self::InputC | Self::InputD | Self::InputE => Ok(None),
+            Self::Int(_) | Self::Float(_) => Ok(Some(N)),
+            Self::Sin(val)
+            | Self::Cos(val)
+            | Self::Tan(val)
+            | Self::Sqrt(val)
+            | Self::Asin(val)
+            | Self::Acos(val)
+            | Self::Atan(val)
+            | Self::Exp(val)
+            | Self::Log(val)
+            | Self::Sign(val)
+            | Self::Round(val)
+            | Self::Floor(val)
+            | Self::Ceil(val)
+            | Self::Output(val)
+            | Self::Not(val) => check(&[val], &[(&[N], N)]),
+            Self::Atan2(val1, val2)
+            | Self::Pow(val1, val2)
+            | Self::Max2(val1, val2)
+            | Self::Min2(val1, val2)
+            | Self::Mod(val1, val2)
+            | Self::Eq(val1, val2)
+            | Self::Ne(val1, val2)
+            | Self::Gt(val1, val2)
+            | Self::Gte(val1, val2)
+            | Self::Lt(val1, val2)
+            | Self::Lte(val1, val2)
+            | Self::OpAnd(val1, val2)
+            | Self::OpOr(val1, val2)
+            | Self::FalseCoalesce(val1, val2) => check(&[val1, val2], &[(&[N, N], N)]),
+            Self::Max3(val1, val2, val3) | Self::Min3(val1, val2, val3) => check(&[val1, val2, val3], &[(&[N, N, N], N)]),
+            Self::Abs(val) | Self::Negate(val) => check(&[val], &[(&[N], N), (&[V], V)]),
+            Self::MaxV(val)
+            | Self::MinV(val)
+            | Self::GetX(val)
+            | Self::GetY(val)
+            | Self::GetZ(val)
+            | Self::Magnitude(val)
+            | Self::SquareMagnitude(val) => check(&[val], &[(&[V], N)]),
+            Self::If {
+                condition,
+                true_value,
+                false_value,
+            } => check(
+                &[condition, true_value, false_value],
+                &[(&[N, N, N], N), (&[N, V, V], V), (&[N, Q, Q], Q), (&[N, S, S], S)],
+            ),
+            Self::Vector(x, y, z) => check(&[x, y, z], &[(&[N, N, N], V)]),
+            Self::MakeRotationBetween {
+                from_vector,
+                to_vector,
+            } => check(&[from_vector, to_vector], &[(&[V, V], Q)]),
+            Self::FromEuler {
+                pitch,
+                yaw,
+                roll,
+            } => check(&[pitch, yaw, roll], &[(&[N, N, N], Q)]),
+            Self::FromEularV(val) => check(&[val], &[(&[V], Q)]),
+            Self::ToEularV(val) | Self::Axis(val) => check(&[val], &[(&[Q], V)]),
+            Self::Angle(val) => check(&[val], &[(&[Q], N)]),
+            Self::AngleBetween {
+                from_vector,
+                to_vector,
+            } => check(&[from_vector, to_vector], &[(&[V, V], N)]),
+            Self::SetX {
+                vector,
+                x: component,
+            }
+            | Self::SetY {
+                vector,
+                y: component,
+            }
+            | Self::SetZ {
+                vector,
+                z: component,
+            } => check(&[vector, component], &[(&[V, N], V)]),
+            Self::OutputV(val) => check(&[val], &[(&[N], V)]),
+            Self::RotationInverse(val) => check(&[val], &[(&[Q], Q)]),
+            Self::Add(lhs, rhs) | Self::Sub(lhs, rhs) => check(&[lhs, rhs], &[(&[N, N], N), (&[V, V], V), (&[S, S], S)]),
+            Self::Cross(lhs, rhs) => check(&[lhs, rhs], &[(&[V, V], V)]),
+            Self::Mul(lhs, rhs) => check(
+                &[lhs, rhs],
+                &[
+                    (&[N, N], N),
+                    (&[V, V], N),
+                    (&[Q, V], V),
+                    (&[V, Q], V),
+                    (&[N, V], V),
+                    (&[V, N], V),
+                    (&[Q, Q], Q),
+                ],
+            ),
+            Self::Div(lhs, rhs) => check(&[lhs, rhs], &[(&[N, N], N), (&[V, N], V), (&[V, Q], V)]),
+        }
+    }
+}
@@ -0,0 +1,426 @@
+//! Rebuilding a breadboard from blueprint data
+//!
+//! Only the component kinds this crate can emit are supported. Entries of a component which
+//! the rebuilt component does not write itself (such as its position) are kept as section overlays,
+//! so saving the breadboard again preserves them.
+
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::ftd_data::{ftd_uuid_to_uuid, BlockData, BlueprintData, DataEntry, DataEntryId, SectionData, SectionId};
+use super::component_uuid::*;
+use super::evaluator::Evaluator;
+use super::{
+    Altitude,
+    AltitudeOutputType,
+    Breadboard,
+    Component,
+    Constant,
+    ImportError,
+    LineInner,
+    MAIN_SECTION_ID,
+    Multiply,
+    Position,
+    RandomInput,
+    Speed,
+    SpeedOutputType,
+    Switch,
+    TargetInfo,
+};
+
+/// Entry ids used by every component for its wiring
+const INPUTS_ENTRY_ID: DataEntryId = 900;
+const OUTPUTS_ENTRY_ID: DataEntryId = 901;
+
+/// A component read from the blueprint whose inputs have not been resolved yet
+struct SavedComponent<'a> {
+    ftd_uuid: Uuid,
+    section: &'a SectionData,
+    /// Uuids of the outputs each input is connected to
+    input_uuids: Vec<Uuid>,
+    output_uuids: Vec<Uuid>,
+}
+
+impl Breadboard {
+    /// Rebuilds a breadboard from blueprint data, such as the data built by [`blueprint_data`](Self::blueprint_data)
+    ///
+    /// If several blocks contain a breadboard, the one with the lowest block index is used.
+    /// Since ftd does not store the types of wires, the rebuilt breadboard has no typed lines,
+    /// but it can be saved again with its components and wiring unchanged.
+    pub fn from_blueprint(data: &BlueprintData) -> Result<Breadboard, ImportError> {
+        let block = data.blocks()
+            .filter(|(_, block)| block.section_data(MAIN_SECTION_ID.into()).is_some())
+            .min_by_key(|(index, _)| *index)
+            .map(|(_, block)| block)
+            .ok_or(ImportError::NoBreadboard)?;
+
+        let saved_components = read_saved_components(block)?;
+
+        // all outputs are collected first, since inputs may be connected to components later in the list
+        let mut output_lines = HashMap::new();
+        for (component_index, saved_component) in saved_components.iter().enumerate() {
+            for (output_index, output_uuid) in saved_component.output_uuids.iter().enumerate() {
+                output_lines.insert(*output_uuid, LineInner {
+                    component_index,
+                    output_index,
+                });
+            }
+        }
+
+        let breadboard = Breadboard::new();
+        for (component_index, saved_component) in saved_components.into_iter().enumerate() {
+            let inputs = saved_component.input_uuids.iter()
+                .map(|uuid| output_lines.get(uuid).copied().ok_or(ImportError::UnknownWire { component_index }))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let component = import_component(component_index, saved_component.ftd_uuid, saved_component.section, inputs)?;
+
+            if component.num_outputs() != saved_component.output_uuids.len() {
+                return Err(ImportError::InvalidEntry {
+                    component_index,
+                    entry_id: OUTPUTS_ENTRY_ID,
+                });
+            }
+
+            let written_entries = component.section_data();
+            let mut overlay = SectionData::default();
+            for (id, entry) in saved_component.section.entries() {
+                if id != INPUTS_ENTRY_ID && id != OUTPUTS_ENTRY_ID && written_entries.entry(id).is_none() {
+                    overlay.add_entry(id, entry.clone());
+                }
+            }
+
+            breadboard.insert_boxed_component(component);
+            if !overlay.is_empty() {
+                breadboard.0.section_overlays.borrow_mut().insert(component_index, overlay);
+            }
+        }
+
+        Ok(breadboard)
+    }
+}
+
+/// Reads the component list from the main section of the breadboard block
+fn read_saved_components(block: &BlockData) -> Result<Vec<SavedComponent<'_>>, ImportError> {
+    let main_section = block.section_data(MAIN_SECTION_ID.into())
+        .ok_or(ImportError::NoBreadboard)?;
+
+    let mut saved_components = Vec::new();
+    for component_index in 0.. {
+        let Ok(uuid_entry_id) = DataEntryId::try_from(2 * component_index) else {
+            break;
+        };
+        let Some(uuid_entry) = main_section.entry(uuid_entry_id) else {
+            break;
+        };
+
+        let id_entry_id = uuid_entry_id + 1;
+        let invalid_list = ImportError::InvalidComponentList {
+            entry_id: id_entry_id,
+        };
+
+        let DataEntry::Uuid(uuid) = uuid_entry else {
+            return Err(ImportError::InvalidComponentList {
+                entry_id: uuid_entry_id,
+            });
+        };
+        let Some(DataEntry::U32(section_id)) = main_section.entry(id_entry_id) else {
+            return Err(invalid_list);
+        };
+        let Ok(section_id) = SectionId::try_new(*section_id) else {
+            return Err(invalid_list);
+        };
+
+        let section = block.section_data(section_id)
+            .ok_or(ImportError::MissingComponentSection { component_index })?;
+
+        let input_uuids = read_uuids(section, component_index, INPUTS_ENTRY_ID)?
+            .into_iter()
+            // every input is a pair of uuids, and the second one is the output it is connected to
+            .skip(1)
+            .step_by(2)
+            .collect();
+        let output_uuids = read_uuids(section, component_index, OUTPUTS_ENTRY_ID)?;
+
+        saved_components.push(SavedComponent {
+            // the conversion only swaps bytes, so applying it again undoes it
+            ftd_uuid: ftd_uuid_to_uuid(*uuid),
+            section,
+            input_uuids,
+            output_uuids,
+        });
+    }
+
+    Ok(saved_components)
+}
+
+/// Reads an entry of concatenated uuids, a missing entry is treated as empty
+fn read_uuids(section: &SectionData, component_index: usize, entry_id: DataEntryId) -> Result<Vec<Uuid>, ImportError> {
+    let invalid_entry = ImportError::InvalidEntry {
+        component_index,
+        entry_id,
+    };
+
+    let bytes = match section.entry(entry_id) {
+        Some(DataEntry::Bytes(bytes)) => bytes.as_slice(),
+        Some(_) => return Err(invalid_entry),
+        None => &[],
+    };
+
+    // inputs are stored in pairs, so both entries must be a whole number of uuids and an even number for inputs
+    let chunk_size = if entry_id == INPUTS_ENTRY_ID { 32 } else { 16 };
+    if bytes.len() % chunk_size != 0 {
+        return Err(invalid_entry);
+    }
+
+    Ok(bytes.chunks_exact(16)
+        .map(|chunk| Uuid::from_slice(chunk).unwrap())
+        .collect())
+}
+
+fn import_component(
+    component_index: usize,
+    ftd_uuid: Uuid,
+    section: &SectionData,
+    inputs: Vec<LineInner>,
+) -> Result<Box<dyn Component>, ImportError> {
+    let invalid_entry = |entry_id| ImportError::InvalidEntry {
+        component_index,
+        entry_id,
+    };
+    let f32_entry = |entry_id| match section.entry(entry_id) {
+        Some(DataEntry::F32(n)) => Ok(*n),
+        _ => Err(invalid_entry(entry_id)),
+    };
+    let u32_entry = |entry_id| match section.entry(entry_id) {
+        Some(DataEntry::U32(n)) => Ok(*n),
+        _ => Err(invalid_entry(entry_id)),
+    };
+
+    let input_count = inputs.len();
+    let expect_inputs = |count| if input_count == count {
+        Ok(())
+    } else {
+        Err(ImportError::WrongInputCount {
+            component_index,
+            count: input_count,
+        })
+    };
+
+    let component: Box<dyn Component> = match ftd_uuid {
+        CONSTANT_UUID => {
+            expect_inputs(0)?;
+            Box::new(Constant {
+                n: f32_entry(0)?,
+            })
+        },
+        RANDOM_INPUT_UUID => {
+            expect_inputs(0)?;
+            let Some(DataEntry::Vector2(range)) = section.entry(0) else {
+                return Err(invalid_entry(0));
+            };
+
+            Box::new(RandomInput {
+                min: range.x,
+                max: range.y,
+            })
+        },
+        ALTITUDE_UUID => {
+            expect_inputs(0)?;
+            let typ = match u32_entry(0)? {
+                0 => AltitudeOutputType::SeaLevel,
+                1 => AltitudeOutputType::WaveLevel,
+                2 => AltitudeOutputType::TerrainLevel,
+                3 => AltitudeOutputType::TerrainAndWave,
+                4 => AltitudeOutputType::TerrainAndSea,
+                _ => return Err(invalid_entry(0)),
+            };
+
+            Box::new(Altitude {
+                typ,
+            })
+        },
+        POSITION_UUID => {
+            expect_inputs(0)?;
+            Box::new(Position)
+        },
+        // speed and velocity are saved the same way, so this is always rebuilt as speed
+        SPEED_UUID => {
+            expect_inputs(0)?;
+            let typ = match u32_entry(0)? {
+                0 => SpeedOutputType::Magnitude,
+                3 => SpeedOutputType::ForwardsMagnitude,
+                _ => return Err(invalid_entry(0)),
+            };
+
+            Box::new(Speed {
+                typ,
+            })
+        },
+        TARGET_INFO_UUID => {
+            expect_inputs(0)?;
+            Box::new(TargetInfo)
+        },
+        MULTIPLY_UUID => Box::new(Multiply {
+            multiplier: f32_entry(0)?,
+            inputs,
+        }),
+        SWITCH_UUID => {
+            expect_inputs(2)?;
+            Box::new(Switch {
+                inputs: [inputs[0], inputs[1]],
+                threshold: f32_entry(0)?,
+                open_value: f32_entry(1)?,
+            })
+        },
+        EVALUATOR_UUID => {
+            let Some(DataEntry::String(expr_string)) = section.entry(0) else {
+                return Err(invalid_entry(0));
+            };
+
+            let evaluator = Evaluator::from_saved(inputs, expr_string)
+                .map_err(|error| ImportError::InvalidExpression {
+                    component_index,
+                    error,
+                })?;
+
+            Box::new(evaluator)
+        },
+        _ => return Err(ImportError::UnsupportedComponent {
+            component_index,
+            ftd_uuid,
+        }),
+    };
+
+    Ok(component)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::breadboard::{COMPONENT_ID_START, EMPTY_SECTION_IDS, SwitchOptions};
+
+    /// Breadboard using every component kind which is not a sensor, fed by a target info sensor
+    fn mixed_breadboard() -> Breadboard {
+        let breadboard = Breadboard::new();
+        let target = breadboard.target_info();
+        let constant = breadboard.constant(-2.5);
+        let random = breadboard.random_number(-1.0, 3.0);
+        let product = breadboard.multiply(&(target.distance, constant, random), 0.5);
+        let switched = breadboard.switch(product, target.present, SwitchOptions::new(0.25, -4.0));
+        let _ = breadboard.clamp01(switched);
+        breadboard
+    }
+
+    /// Entries of every section in the breadboard block, with each wire written as the indices of the output it reads
+    ///
+    /// The serialized bytes can't be compared directly, since wire uuids are random on every save
+    /// and sections and entries are written in hash map order.
+    fn normalized_sections(block: &BlockData) -> Vec<(u32, Vec<(DataEntryId, String)>)> {
+        let main_section = block.section_data(MAIN_SECTION_ID.into()).unwrap();
+        let component_count = main_section.entries().count() / 2;
+        let component_section = |i: usize| block.section_data((COMPONENT_ID_START + i as u32).into()).unwrap();
+
+        let mut output_names = HashMap::new();
+        for i in 0..component_count {
+            for (output_index, uuid) in read_uuids(component_section(i), i, OUTPUTS_ENTRY_ID).unwrap().into_iter().enumerate() {
+                output_names.insert(uuid, format!("output {output_index} of component {i}"));
+            }
+        }
+
+        let section_ids = EMPTY_SECTION_IDS.into_iter()
+            .chain([MAIN_SECTION_ID])
+            .chain((0..component_count).map(|i| COMPONENT_ID_START + i as u32));
+
+        section_ids.map(|section_id| {
+            let section = block.section_data(section_id.into()).unwrap();
+            let mut entries: Vec<_> = section.entries()
+                .map(|(id, entry)| {
+                    let entry = match id {
+                        INPUTS_ENTRY_ID | OUTPUTS_ENTRY_ID if section_id != MAIN_SECTION_ID => {
+                            let uuids = read_uuids(section, 0, id).unwrap();
+                            let wires = if id == INPUTS_ENTRY_ID { uuids.into_iter().skip(1).step_by(2).collect() } else { uuids };
+                            format!("{:?}", wires.iter().map(|uuid| &output_names[uuid]).collect::<Vec<_>>())
+                        },
+                        _ => format!("{entry:?}"),
+                    };
+                    (id, entry)
+                })
+                .collect();
+            entries.sort();
+
+            (section_id, entries)
+        }).collect()
+    }
+
+    #[test]
+    fn saving_an_imported_breadboard_gives_the_same_block() {
+        let breadboard = mixed_breadboard();
+        breadboard.set_component_entry(&breadboard.constant(1.0), 7, DataEntry::Bool(true));
+        let data = breadboard.blueprint_data().unwrap();
+
+        let imported = Breadboard::from_blueprint(&data).unwrap();
+        let resaved = imported.blueprint_data().unwrap();
+
+        let block = data.block_data(0.into()).unwrap();
+        let resaved_block = resaved.block_data(0.into()).unwrap();
+        assert_eq!(normalized_sections(resaved_block), normalized_sections(block));
+        assert_eq!(resaved.serialize().unwrap().len(), data.serialize().unwrap().len());
+    }
+
+    /// Block of [`mixed_breadboard`], with the section `section_id` changed by `f`
+    fn changed_block(section_id: u32, f: impl FnOnce(&mut SectionData)) -> BlueprintData {
+        let mut block = mixed_breadboard().block_data().unwrap();
+        let mut section = block.section_data(section_id.into()).unwrap().clone();
+        f(&mut section);
+        block.add_section_data(section_id.into(), section);
+
+        let mut data = BlueprintData::default();
+        data.add_block_data(0.into(), block);
+        data
+    }
+
+    fn import_error(data: &BlueprintData) -> ImportError {
+        Breadboard::from_blueprint(data).err().unwrap()
+    }
+
+    #[test]
+    fn import_errors() {
+        // components of mixed_breadboard: 0 target info, 1 constant, 2 random, 3 multiply, 4 switch, 5 evaluator
+        let component = |i: u32| COMPONENT_ID_START + i;
+
+        assert_eq!(import_error(&BlueprintData::default()), ImportError::NoBreadboard);
+
+        let data = changed_block(MAIN_SECTION_ID, |section| section.add_entry(2, DataEntry::U32(0)));
+        assert_eq!(import_error(&data), ImportError::InvalidComponentList { entry_id: 2 });
+
+        let data = changed_block(MAIN_SECTION_ID, |section| section.add_entry(3, DataEntry::Bool(true)));
+        assert_eq!(import_error(&data), ImportError::InvalidComponentList { entry_id: 3 });
+
+        let data = changed_block(MAIN_SECTION_ID, |section| section.add_entry(3, DataEntry::U32(component(100))));
+        assert_eq!(import_error(&data), ImportError::MissingComponentSection { component_index: 1 });
+
+        let data = changed_block(MAIN_SECTION_ID, |section| section.add_entry(2, DataEntry::Uuid(Uuid::nil())));
+        assert_eq!(import_error(&data), ImportError::UnsupportedComponent { component_index: 1, ftd_uuid: Uuid::nil() });
+
+        let data = changed_block(component(1), |section| section.add_entry(0, DataEntry::U32(1)));
+        assert_eq!(import_error(&data), ImportError::InvalidEntry { component_index: 1, entry_id: 0 });
+
+        let data = changed_block(component(4), |section| section.add_entry(INPUTS_ENTRY_ID, DataEntry::Bytes(vec![0; 16])));
+        assert_eq!(import_error(&data), ImportError::InvalidEntry { component_index: 4, entry_id: INPUTS_ENTRY_ID });
+
+        // the constant is listed as a switch, which needs 2 inputs
+        let data = changed_block(MAIN_SECTION_ID, |section| section.add_entry(2, DataEntry::Uuid(ftd_uuid_to_uuid(SWITCH_UUID))));
+        assert_eq!(import_error(&data), ImportError::WrongInputCount { component_index: 1, count: 0 });
+
+        let data = changed_block(component(4), |section| section.add_entry(INPUTS_ENTRY_ID, DataEntry::Bytes(vec![1; 64])));
+        assert_eq!(import_error(&data), ImportError::UnknownWire { component_index: 4 });
+
+        let data = changed_block(component(5), |section| section.add_entry(0, DataEntry::String(String::from("a +"))));
+        let ImportError::InvalidExpression { component_index: 5, error } = import_error(&data) else {
+            panic!("expected an expression error");
+        };
+        assert_eq!(error.position(), 3);
+    }
+}
@@ -4,15 +4,27 @@ use std::iter;
 use std::marker::PhantomData;
 use std::ops::{Add, Sub, Mul, Div, Rem, Neg, Not, BitAnd, BitOr};
 
-use super::Breadboard;
+use super::{Breadboard, EvaluatorExpression};
 
 /// Represents the output line of a certain breadboard component
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct LineInner {
     pub(crate) component_index: usize,
     pub(crate) output_index: usize,
 }
 
+impl LineInner {
+    /// Index of the component in its breadboard, components are numbered in the order they were added
+    pub fn component_index(&self) -> usize {
+        self.component_index
+    }
+
+    /// Index of this output among the outputs of its component
+    pub fn output_index(&self) -> usize {
+        self.output_index
+    }
+}
+
 pub struct Line<T: LineValue + ?Sized> {
     pub(crate) inner: LineInner,
     // the line pretends it owns a type T inside the breadboard in its wires
@@ -31,6 +43,21 @@ impl<T: LineValue + ?Sized> Line<T> {
             _marker: PhantomData,
         }
     }
+
+    /// Untyped output this line represents
+    pub fn inner(&self) -> LineInner {
+        self.inner
+    }
+
+    /// Index of the component which outputs this line, see [`LineInner::component_index`]
+    pub fn component_index(&self) -> usize {
+        self.inner.component_index
+    }
+
+    /// Index of this output among the outputs of its component
+    pub fn output_index(&self) -> usize {
+        self.inner.output_index
+    }
 }
 
 impl<T: LineValue + ?Sized> Clone for Line<T> {
@@ -87,6 +114,40 @@ line_op!(Rem, rem, modulo, BNumber, BNumber, BNumber);
 line_op!(BitAnd, bitand, and, BNumber, BNumber, BNumber);
 line_op!(BitOr, bitor, or, BNumber, BNumber, BNumber);
 
+/// Operators between a number line and an f32, done by an evaluator with the f32 written into its expression
+///
+/// The f32 is a literal in the expression instead of a constant component,
+/// so it is not clamped to [`COMPONENT_VALUE_LIMIT`](super::COMPONENT_VALUE_LIMIT).
+macro_rules! line_constant_op {
+    ($trait:ident, $method:ident) => {
+        impl $trait<f32> for &Line<BNumber> {
+            type Output = Line<BNumber>;
+
+            fn $method(self, rhs: f32) -> Self::Output {
+                self.breadboard.evaluator_expr(self.clone(), |lhs| {
+                    $trait::$method(*lhs, EvaluatorExpression::Float(rhs.into()))
+                })
+            }
+        }
+
+        impl $trait<&Line<BNumber>> for f32 {
+            type Output = Line<BNumber>;
+
+            fn $method(self, rhs: &Line<BNumber>) -> Self::Output {
+                rhs.breadboard.evaluator_expr(rhs.clone(), |rhs| {
+                    $trait::$method(EvaluatorExpression::Float(self.into()), *rhs)
+                })
+            }
+        }
+    };
+}
+
+line_constant_op!(Add, add);
+line_constant_op!(Sub, sub);
+line_constant_op!(Mul, mul);
+line_constant_op!(Div, div);
+line_constant_op!(Rem, rem);
+
 impl Not for &Line<BNumber> {
     type Output = Line<BNumber>;
 
@@ -103,6 +164,56 @@ impl Neg for &Line<BNumber> {
     }
 }
 
+impl Line<BNumber> {
+    pub fn abs(&self) -> Line<BNumber> {
+        self.breadboard.abs(self.clone())
+    }
+
+    pub fn sqrt(&self) -> Line<BNumber> {
+        self.breadboard.sqrt(self.clone())
+    }
+
+    pub fn min(&self, other: &Self) -> Line<BNumber> {
+        self.breadboard.min2(self.clone(), other.clone())
+    }
+
+    pub fn max(&self, other: &Self) -> Line<BNumber> {
+        self.breadboard.max2(self.clone(), other.clone())
+    }
+
+    /// See [`Breadboard::clamp`]
+    pub fn clamp(&self, min: &Self, max: &Self) -> Line<BNumber> {
+        self.breadboard.clamp(self.clone(), min.clone(), max.clone())
+    }
+
+    /// The larger of this line and `min`, so the output is never below `min`
+    ///
+    /// Like the operators with an f32, this is a single evaluator with `min` written into its expression,
+    /// so `min` is not clamped to [`COMPONENT_VALUE_LIMIT`](super::COMPONENT_VALUE_LIMIT).
+    ///
+    /// ```
+    /// use bakery::prelude::*;
+    ///
+    /// let breadboard = Breadboard::new();
+    /// let altitude = breadboard.altitude(AltitudeOutputType::SeaLevel);
+    ///
+    /// // altitude limited to the range [0, 500]
+    /// let _ = altitude.at_least(0.0).at_most(500.0);
+    /// ```
+    pub fn at_least(&self, min: f32) -> Line<BNumber> {
+        self.breadboard.evaluator_expr(self.clone(), |x| {
+            EvaluatorExpression::Max2(x, Box::new(EvaluatorExpression::Float(min.into())))
+        })
+    }
+
+    /// The smaller of this line and `max`, so the output is never above `max`, see [`at_least`](Self::at_least)
+    pub fn at_most(&self, max: f32) -> Line<BNumber> {
+        self.breadboard.evaluator_expr(self.clone(), |x| {
+            EvaluatorExpression::Min2(x, Box::new(EvaluatorExpression::Float(max.into())))
+        })
+    }
+}
+
 impl Line<BVector3> {
     pub fn cross(&self, rhs: &Self) -> Line<BVector3> {
         self.breadboard.cross(self.clone(), rhs.clone())
@@ -131,24 +242,103 @@ impl Line<BVector3> {
     pub fn square_magnitude(&self) -> Line<BNumber> {
         self.breadboard.square_magnitude(self.clone())
     }
+
+    /// See [`Breadboard::any_perpendicular`]
+    pub fn any_perpendicular(&self) -> Line<BVector3> {
+        self.breadboard.any_perpendicular(self.clone())
+    }
 }
 
 impl Line<BQuaternion> {
     pub fn inverse(&self) -> Line<BQuaternion> {
         self.breadboard.rotation_inverse(self.clone())
     }
+
+    /// Forward (+z) direction of this rotation, see [`Breadboard::rotation_forward`]
+    pub fn forward(&self) -> Line<BVector3> {
+        self.breadboard.rotation_forward(self.clone())
+    }
+
+    /// Up (+y) direction of this rotation, see [`Breadboard::rotation_up`]
+    pub fn up(&self) -> Line<BVector3> {
+        self.breadboard.rotation_up(self.clone())
+    }
+
+    /// Right (+x) direction of this rotation, see [`Breadboard::rotation_right`]
+    pub fn right(&self) -> Line<BVector3> {
+        self.breadboard.rotation_right(self.clone())
+    }
 }
 
 pub fn b_if<T: LineValue + ?Sized>(condition: &Line<BNumber>, true_value: &Line<T>, false_value: &Line<T>) -> Line<T> {
     condition.breadboard.b_if(condition.clone(), true_value.clone(), false_value.clone())
 }
 
+/// Builds a vector from its components, on the breadboard the lines are from, see [`Breadboard::vector`]
+///
+/// # Panics
+///
+/// Panics if the lines are not all from the same breadboard.
+pub fn vec3(x: &Line<BNumber>, y: &Line<BNumber>, z: &Line<BNumber>) -> Line<BVector3> {
+    x.breadboard.vector(x.clone(), y.clone(), z.clone())
+}
+
 
 mod private {
     pub trait Sealed {}
 }
 
-pub trait LineValue: private::Sealed {}
+/// Type of the value sent over a [`Line`]
+///
+/// Methods and operators only accept lines of the types the underlying expression supports,
+/// so wiring mistakes are compile errors. A rotation can't be used as a number:
+///
+/// ```compile_fail
+/// use bakery::prelude::*;
+///
+/// let breadboard = Breadboard::new();
+/// let rotation = breadboard.rotation_from_euler_vector(breadboard.new_vector(0.0, 90.0, 0.0));
+/// let n = breadboard.constant(1.0);
+/// let _ = &rotation + &n;
+/// ```
+///
+/// Numbers and vectors can't be added:
+///
+/// ```compile_fail
+/// use bakery::prelude::*;
+///
+/// let breadboard = Breadboard::new();
+/// let _ = breadboard.add(breadboard.constant(1.0), breadboard.position());
+/// ```
+///
+/// Both branches of an if must have the same type:
+///
+/// ```compile_fail
+/// use bakery::prelude::*;
+///
+/// let breadboard = Breadboard::new();
+/// let condition = breadboard.constant(1.0);
+/// let _ = b_if(&condition, &breadboard.constant(2.0), &breadboard.position());
+/// ```
+///
+/// And a vector can't be used as a switch signal:
+///
+/// ```compile_fail
+/// use bakery::prelude::*;
+///
+/// let breadboard = Breadboard::new();
+/// let _ = breadboard.switch(breadboard.constant(1.0), breadboard.position(), SwitchOptions::default());
+/// ```
+///
+/// The trait is sealed, so no other types can be sent over lines:
+///
+/// ```compile_fail
+/// use bakery::prelude::*;
+///
+/// struct Color;
+/// impl LineValue for Color {}
+/// ```
+pub trait LineValue: private::Sealed + 'static {}
 
 pub struct BNumber;
 
@@ -171,63 +361,88 @@ impl private::Sealed for BString {}
 impl LineValue for BString {}
 
 
-// FIXME: verify line group is from same breadboard
 pub trait InputGroup<T: LineValue> {
-    fn iter_inputs(&self) -> impl Iterator<Item = LineInner>;
+    fn iter_lines(&self) -> impl Iterator<Item = &Line<T>>;
+
+    fn iter_inputs(&self) -> impl Iterator<Item = LineInner> {
+        self.iter_lines().map(|line| line.inner)
+    }
 
     fn as_vec(&self) -> Vec<LineInner> {
         self.iter_inputs().collect()
     }
+
+    /// Number of lines in the group, counting a line which appears more than once each time
+    fn len(&self) -> usize {
+        self.iter_lines().count()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Line at index `i` of the group, in the same order as [`iter_lines`](Self::iter_lines)
+    fn get(&self, i: usize) -> Option<LineInner> {
+        self.iter_inputs().nth(i)
+    }
+
+    fn first(&self) -> Option<LineInner> {
+        self.get(0)
+    }
+
+    fn last(&self) -> Option<LineInner> {
+        self.iter_inputs().last()
+    }
 }
 
 impl<T: LineValue> InputGroup<T> for Line<T> {
-    fn iter_inputs(&self) -> impl Iterator<Item = LineInner> {
-        iter::once(self.inner)
+    fn iter_lines(&self) -> impl Iterator<Item = &Line<T>> {
+        iter::once(self)
     }
 }
 
 impl<T: LineValue> InputGroup<T> for (Line<T>,) {
-    fn iter_inputs(&self) -> impl Iterator<Item = LineInner> {
-        iter::once(self.0.inner)
+    fn iter_lines(&self) -> impl Iterator<Item = &Line<T>> {
+        iter::once(&self.0)
     }
 }
 
 impl<T: LineValue> InputGroup<T> for (Line<T>, Line<T>) {
-    fn iter_inputs(&self) -> impl Iterator<Item = LineInner> {
-        iter::once(self.0.inner)
-            .chain(iter::once(self.1.inner))
+    fn iter_lines(&self) -> impl Iterator<Item = &Line<T>> {
+        iter::once(&self.0)
+            .chain(iter::once(&self.1))
     }
 }
 
 impl<T: LineValue> InputGroup<T> for (Line<T>, Line<T>, Line<T>) {
-    fn iter_inputs(&self) -> impl Iterator<Item = LineInner> {
-        iter::once(self.0.inner)
-            .chain(iter::once(self.1.inner))
-            .chain(iter::once(self.2.inner))
+    fn iter_lines(&self) -> impl Iterator<Item = &Line<T>> {
+        iter::once(&self.0)
+            .chain(iter::once(&self.1))
+            .chain(iter::once(&self.2))
     }
 }
 
 impl<T: LineValue> InputGroup<T> for (Line<T>, Line<T>, Line<T>, Line<T>) {
-    fn iter_inputs(&self) -> impl Iterator<Item = LineInner> {
-        iter::once(self.0.inner)
-            .chain(iter::once(self.1.inner))
-            .chain(iter::once(self.2.inner))
-            .chain(iter::once(self.3.inner))
+    fn iter_lines(&self) -> impl Iterator<Item = &Line<T>> {
+        iter::once(&self.0)
+            .chain(iter::once(&self.1))
+            .chain(iter::once(&self.2))
+            .chain(iter::once(&self.3))
     }
 }
 
 impl<T: LineValue> InputGroup<T> for (Line<T>, Line<T>, Line<T>, Line<T>, Line<T>) {
-    fn iter_inputs(&self) -> impl Iterator<Item = LineInner> {
-        iter::once(self.0.inner)
-            .chain(iter::once(self.1.inner))
-            .chain(iter::once(self.2.inner))
-            .chain(iter::once(self.3.inner))
-            .chain(iter::once(self.4.inner))
+    fn iter_lines(&self) -> impl Iterator<Item = &Line<T>> {
+        iter::once(&self.0)
+            .chain(iter::once(&self.1))
+            .chain(iter::once(&self.2))
+            .chain(iter::once(&self.3))
+            .chain(iter::once(&self.4))
     }
 }
 
 impl<T: LineValue> InputGroup<T> for [Line<T>] {
-    fn iter_inputs(&self) -> impl Iterator<Item = LineInner> {
-        self.iter().map(|line| line.inner)
+    fn iter_lines(&self) -> impl Iterator<Item = &Line<T>> {
+        self.iter()
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,991 @@
+//! Higher level math helpers which are built from a single evaluator
+//!
+//! Like the rest of ftd all angles are in degrees.
+
+use super::{BNumber, BQuaternion, BVector3, Breadboard, InputGroup, Line, TargetInfoOutputs, VelocityOutputType};
+use super::evaluator::{Evaluator, EvaluatorExpression};
+
+/// Below this the quadratic term of the intercept equation is treated as 0
+const INTERCEPT_EPSILON: f64 = 0.0001;
+
+/// Inputs of activation functions are clamped to this magnitude so `Exp` can't overflow into `inf / inf`
+///
+/// Both functions are within 1e-8 of their limits at this point, even with f32 precision.
+const ACTIVATION_INPUT_LIMIT: f64 = 20.0;
+
+/// Denominators with a magnitude at or below this are treated as 0 by the safe division helpers
+const DIVISION_EPSILON: f64 = 0.000001;
+
+/// Below this fraction of its squared length, the part of the up vector perpendicular to forward is treated as 0
+const LOOK_ROTATION_EPSILON: f64 = 0.000001;
+
+/// Wraps an angle expression into the range `[0, 360)`
+fn wrap_angle_expression(angle: EvaluatorExpression) -> EvaluatorExpression {
+    // % keeps the sign of the dividend, so 360 is added to bring negative remainders into [0, 360)
+    (angle % EvaluatorExpression::Int(360) + EvaluatorExpression::Int(360)) % EvaluatorExpression::Int(360)
+}
+
+/// Signed shortest difference `a - b` between two angle expressions, in the range `[-180, 180)`
+fn angle_diff_expression(a: EvaluatorExpression, b: EvaluatorExpression) -> EvaluatorExpression {
+    wrap_angle_expression(a - b + EvaluatorExpression::Int(180)) - EvaluatorExpression::Int(180)
+}
+
+fn single_axis_euler(pitch: EvaluatorExpression, yaw: EvaluatorExpression, roll: EvaluatorExpression) -> EvaluatorExpression {
+    EvaluatorExpression::FromEuler {
+        pitch: Box::new(pitch),
+        yaw: Box::new(yaw),
+        roll: Box::new(roll),
+    }
+}
+
+impl Breadboard {
+    /// Linearly maps `x` from the range `[in_lo, in_hi]` to the range `[out_lo, out_hi]`
+    ///
+    /// If `clamp` is true the output is clamped to the output range.
+    /// If `in_lo` and `in_hi` are equal the input range is empty, and `out_lo` is output.
+    pub fn remap(
+        &self,
+        x: Line<BNumber>,
+        in_lo: Line<BNumber>,
+        in_hi: Line<BNumber>,
+        out_lo: Line<BNumber>,
+        out_hi: Line<BNumber>,
+        clamp: bool,
+    ) -> Line<BNumber> {
+        let mut eval = Evaluator::default();
+        let [x, in_lo, in_hi, out_lo, out_hi] = self.evaluator_inputs(&mut eval, [&x, &in_lo, &in_hi, &out_lo, &out_hi]);
+
+        let mut mapped = out_lo.clone()
+            + (x - in_lo.clone()) * (out_hi.clone() - out_lo.clone()) / (in_hi.clone() - in_lo.clone());
+
+        if clamp {
+            mapped = EvaluatorExpression::clamp(
+                mapped,
+                EvaluatorExpression::min(out_lo.clone(), out_hi.clone()),
+                EvaluatorExpression::max(out_lo.clone(), out_hi),
+            );
+        }
+
+        eval.add_output(EvaluatorExpression::if_else(
+            EvaluatorExpression::Ne(Box::new(in_hi), Box::new(in_lo)),
+            mapped,
+            out_lo,
+        ));
+
+        self.insert_component_with_output(eval)
+    }
+
+    /// Predicts where a projectile fired from our position now would hit a target moving at a constant velocity
+    ///
+    /// This solves `|target_pos + target_vel * t - our_pos| = projectile_speed * t` for the smallest positive `t`.
+    /// If there is no solution (the target is moving away faster than the projectile can travel),
+    /// the current target position is returned instead.
+    pub fn intercept_point(
+        &self,
+        target_pos: Line<BVector3>,
+        target_vel: Line<BVector3>,
+        projectile_speed: Line<BNumber>,
+    ) -> Line<BVector3> {
+        let our_pos = self.position();
+
+        let mut eval = Evaluator::default();
+        let [target_pos, target_vel, our_pos] = self.evaluator_inputs(&mut eval, [&target_pos, &target_vel, &our_pos]);
+        let [projectile_speed] = self.evaluator_inputs(&mut eval, [&projectile_speed]);
+
+        let offset = target_pos.clone() - our_pos;
+
+        // coefficients of a * t^2 + b * t + c = 0
+        let a = target_vel.clone() * target_vel.clone() - projectile_speed.clone() * projectile_speed;
+        let b = EvaluatorExpression::Int(2) * (offset.clone() * target_vel.clone());
+        let c = offset.clone() * offset;
+
+        let discriminant = b.clone() * b.clone() - EvaluatorExpression::Int(4) * a.clone() * c.clone();
+        let root = EvaluatorExpression::Sqrt(Box::new(discriminant.clone()));
+        let t1 = (-b.clone() + root.clone()) / (EvaluatorExpression::Int(2) * a.clone());
+        let t2 = (-b.clone() - root) / (EvaluatorExpression::Int(2) * a.clone());
+
+        // if the projectile is faster than the target one root is negative and the other is positive,
+        // otherwise both roots have the same sign and the smaller one is hit first
+        let t = EvaluatorExpression::if_else(
+            EvaluatorExpression::Lt(
+                Box::new(EvaluatorExpression::Abs(Box::new(a.clone()))),
+                Box::new(EvaluatorExpression::Float(INTERCEPT_EPSILON)),
+            ),
+            // target speed and projectile speed are equal, so the equation is linear
+            -c / b,
+            EvaluatorExpression::if_else(
+                EvaluatorExpression::Lt(Box::new(a), Box::new(EvaluatorExpression::Int(0))),
+                EvaluatorExpression::max(t1.clone(), t2.clone()),
+                EvaluatorExpression::min(t1, t2),
+            ),
+        );
+
+        let valid = EvaluatorExpression::OpAnd(
+            Box::new(EvaluatorExpression::Gte(Box::new(discriminant), Box::new(EvaluatorExpression::Int(0)))),
+            Box::new(EvaluatorExpression::Gt(Box::new(t.clone()), Box::new(EvaluatorExpression::Int(0)))),
+        );
+
+        eval.add_output(EvaluatorExpression::if_else(
+            valid,
+            target_pos.clone() + t * target_vel,
+            target_pos,
+        ));
+
+        self.insert_component_with_output(eval)
+    }
+
+    /// Rate at which the distance to the target is shrinking, in meters per second
+    ///
+    /// Positive means we are closing on the target and negative means it is getting further away.
+    /// This is the target's velocity relative to ours along the line between us. Outputs 0 when there is no target.
+    ///
+    /// Our velocity comes from [`velocity`](Self::velocity), and this assumes it is in world space like the target's
+    /// velocity and both positions. That has not been checked in ftd, if the speed component's vector mode is relative
+    /// to our rotation instead, the result is only right while we are unrotated.
+    pub fn closing_speed_to_target(&self, target: &TargetInfoOutputs) -> Line<BNumber> {
+        let our_pos = self.position();
+        let our_vel = self.velocity(VelocityOutputType::Magnitude);
+
+        let mut eval = Evaluator::default();
+        let [target_pos, target_vel, our_pos, our_vel] = self.evaluator_inputs(
+            &mut eval,
+            [&target.position, &target.velocity, &our_pos, &our_vel],
+        );
+        let [present] = self.evaluator_inputs(&mut eval, [&target.present]);
+
+        let offset = target_pos - our_pos;
+        let distance = EvaluatorExpression::Magnitude(Box::new(offset.clone()));
+        let relative_vel = target_vel - our_vel;
+
+        // the distance shrinks when the relative velocity points back towards us, against the offset
+        let closing_speed = -(relative_vel * offset) / distance.clone();
+
+        eval.add_output(EvaluatorExpression::if_else(
+            EvaluatorExpression::OpAnd(
+                Box::new(present),
+                Box::new(EvaluatorExpression::Gt(Box::new(distance), Box::new(EvaluatorExpression::Int(0)))),
+            ),
+            closing_speed,
+            EvaluatorExpression::Int(0),
+        ));
+
+        self.insert_component_with_output(eval)
+    }
+
+    /// Horizontal bearing from our craft to a point in world space, relative to our forward direction
+    ///
+    /// The bearing is in the range `[-180, 180]`, with 0 straight ahead and positive to the right,
+    /// matching the bearing output of [`target_info`](Self::target_info).
+    /// `rotation` is our craft's orientation in world space. It is a parameter because this crate has no sensor
+    /// for our orientation, the ftd uuid and output layout of one are not known, so it has to come from elsewhere.
+    pub fn bearing_to(&self, point: Line<BVector3>, rotation: Line<BQuaternion>) -> Line<BNumber> {
+        self.local_direction_angle(point, rotation, |local| {
+            // Atan takes y then x like unity's Mathf.Atan2, forward is z and right is x
+            EvaluatorExpression::Atan2(
+                Box::new(EvaluatorExpression::GetX(Box::new(local.clone()))),
+                Box::new(EvaluatorExpression::GetZ(Box::new(local))),
+            )
+        })
+    }
+
+    /// Compass heading of a direction in world space, in the range `[0, 360)`
+    ///
+    /// Only the horizontal part of `direction` in the x z plane is used, with +z as north (heading 0)
+    /// and +x as east (heading 90), so the heading increases clockwise when seen from above.
+    /// A vertical or zero direction has a heading of 0.
+    pub fn heading_of(&self, direction: Line<BVector3>) -> Line<BNumber> {
+        self.evaluator_expr(direction, |direction| {
+            // Atan takes y then x like unity's Mathf.Atan2, and outputs (-180, 180]
+            let heading = EvaluatorExpression::Atan2(
+                Box::new(EvaluatorExpression::GetX(direction.clone())),
+                Box::new(EvaluatorExpression::GetZ(direction)),
+            );
+
+            (heading + EvaluatorExpression::Int(360)) % EvaluatorExpression::Int(360)
+        })
+    }
+
+    /// Vertical angle from our craft to a point in world space, relative to our horizontal plane
+    ///
+    /// The elevation is in the range `[-90, 90]`, with positive meaning the point is above us.
+    /// `rotation` is our craft's orientation in world space, see [`bearing_to`](Self::bearing_to) for why it is a parameter.
+    pub fn elevation_to(&self, point: Line<BVector3>, rotation: Line<BQuaternion>) -> Line<BNumber> {
+        self.local_direction_angle(point, rotation, |local| {
+            let x = EvaluatorExpression::GetX(Box::new(local.clone()));
+            let z = EvaluatorExpression::GetZ(Box::new(local.clone()));
+            let horizontal_distance = EvaluatorExpression::Sqrt(Box::new(x.clone() * x + z.clone() * z));
+
+            EvaluatorExpression::Atan2(
+                Box::new(EvaluatorExpression::GetY(Box::new(local))),
+                Box::new(horizontal_distance),
+            )
+        })
+    }
+
+    /// Builds an angle from the offset to `point` in our craft's local space
+    fn local_direction_angle(
+        &self,
+        point: Line<BVector3>,
+        rotation: Line<BQuaternion>,
+        angle_fn: impl FnOnce(EvaluatorExpression) -> EvaluatorExpression,
+    ) -> Line<BNumber> {
+        let our_pos = self.position();
+
+        let mut eval = Evaluator::default();
+        let [point, our_pos] = self.evaluator_inputs(&mut eval, [&point, &our_pos]);
+        let [rotation] = self.evaluator_inputs(&mut eval, [&rotation]);
+
+        let local = (point - our_pos) * EvaluatorExpression::RotationInverse(Box::new(rotation));
+        eval.add_output(angle_fn(local));
+
+        self.insert_component_with_output(eval)
+    }
+
+    /// Signed shortest difference `a - b` between two angles, in the range `[-180, 180)`
+    ///
+    /// This is the angle to turn by to get from `b` to `a`, so with bearings a positive difference means `a` is to the right of `b`.
+    /// Opposite angles give -180.
+    pub fn angle_diff(&self, a: Line<BNumber>, b: Line<BNumber>) -> Line<BNumber> {
+        self.evaluator_expr2(a, b, |a, b| angle_diff_expression(*a, *b))
+    }
+
+    /// Wraps an angle into the range `[0, 360)`
+    pub fn normalize_angle(&self, angle: Line<BNumber>) -> Line<BNumber> {
+        self.evaluator_expr(angle, |angle| wrap_angle_expression(*angle))
+    }
+
+    /// Interpolates from angle `a` to angle `b` along the shortest way around, in the range `[0, 360)`
+    ///
+    /// A `t` of 0 gives `a` and 1 gives `b`, with the angle turning by `t` times [`angle_diff(b, a)`](Self::angle_diff)
+    /// from `a`, so interpolating from 350 to 10 goes through 0 instead of 180. `t` is not clamped.
+    /// When `a` and `b` are opposite the interpolation turns in the negative direction.
+    pub fn lerp_angle(&self, a: Line<BNumber>, b: Line<BNumber>, t: Line<BNumber>) -> Line<BNumber> {
+        self.evaluator_expr3(a, b, t, |a, b, t| {
+            let diff = angle_diff_expression(*b, (*a).clone());
+
+            wrap_angle_expression(*a + diff * *t)
+        })
+    }
+
+    /// Rotation which points the z axis along `forward`, with the y axis as close to `up` as possible
+    ///
+    /// This matches unity's `Quaternion.LookRotation`. Unlike [`new_rotation_between`](Self::new_rotation_between)
+    /// this also controls the roll around `forward`. `forward` must not be zero. If `up` is zero or parallel to `forward`
+    /// there is no roll to match, so the rotation has no roll, which is the same rotation as with a world up vector
+    /// unless `forward` is vertical.
+    pub fn look_rotation(&self, forward: Line<BVector3>, up: Line<BVector3>) -> Line<BQuaternion> {
+        let mut eval = Evaluator::default();
+        let [forward, up] = self.evaluator_inputs(&mut eval, [&forward, &up]);
+
+        let x = EvaluatorExpression::GetX(Box::new(forward.clone()));
+        let y = EvaluatorExpression::GetY(Box::new(forward.clone()));
+        let z = EvaluatorExpression::GetZ(Box::new(forward.clone()));
+
+        // euler angles are applied as roll, then pitch, then yaw, and positive pitch points forward down
+        let yaw = EvaluatorExpression::Atan2(Box::new(x.clone()), Box::new(z.clone()));
+        let horizontal_distance = EvaluatorExpression::Sqrt(Box::new(x.clone() * x + z.clone() * z));
+        let pitch = EvaluatorExpression::Atan2(Box::new(-y), Box::new(horizontal_distance));
+
+        // right and up axes of the rotation before roll is applied, yaw is still defined when forward is vertical
+        let right = EvaluatorExpression::Vector(
+            Box::new(EvaluatorExpression::Cos(Box::new(yaw.clone()))),
+            Box::new(EvaluatorExpression::Int(0)),
+            Box::new(-EvaluatorExpression::Sin(Box::new(yaw.clone()))),
+        );
+        let unit_forward = forward.clone() / EvaluatorExpression::Magnitude(Box::new(forward));
+        let unrolled_up = EvaluatorExpression::Cross(Box::new(unit_forward), Box::new(right.clone()));
+
+        // vector multiplication is a dot product
+        let right_part = up.clone() * right;
+        let up_part = up.clone() * unrolled_up;
+
+        let has_roll = EvaluatorExpression::Gt(
+            Box::new(right_part.clone() * right_part.clone() + up_part.clone() * up_part.clone()),
+            Box::new(EvaluatorExpression::Float(LOOK_ROTATION_EPSILON) * EvaluatorExpression::SquareMagnitude(Box::new(up))),
+        );
+        // positive roll turns the up axis towards -right
+        let roll = EvaluatorExpression::if_else(
+            has_roll,
+            EvaluatorExpression::Atan2(Box::new(-right_part), Box::new(up_part)),
+            EvaluatorExpression::Int(0),
+        );
+
+        eval.add_output(EvaluatorExpression::FromEuler {
+            pitch: Box::new(pitch),
+            yaw: Box::new(yaw),
+            roll: Box::new(roll),
+        });
+
+        self.insert_component_with_output(eval)
+    }
+
+    /// Rotation around the vertical axis only, by `heading` degrees
+    ///
+    /// This is `FromEuler(0, heading, 0)`. Positive headings turn forward towards the right, so a heading from
+    /// [`heading_of`](Self::heading_of) rotates the z axis to that direction.
+    pub fn yaw_rotation(&self, heading: Line<BNumber>) -> Line<BQuaternion> {
+        self.evaluator_expr(heading, |heading| single_axis_euler(EvaluatorExpression::Int(0), *heading, EvaluatorExpression::Int(0)))
+    }
+
+    /// Rotation around the right axis only, by `pitch` degrees
+    ///
+    /// This is `FromEuler(pitch, 0, 0)`. Like the rest of ftd's euler angles, positive pitch points forward down.
+    pub fn pitch_rotation(&self, pitch: Line<BNumber>) -> Line<BQuaternion> {
+        self.evaluator_expr(pitch, |pitch| single_axis_euler(*pitch, EvaluatorExpression::Int(0), EvaluatorExpression::Int(0)))
+    }
+
+    /// Rotation around the forward axis only, by `roll` degrees
+    ///
+    /// This is `FromEuler(0, 0, roll)`. Positive roll turns the up axis towards the left.
+    pub fn roll_rotation(&self, roll: Line<BNumber>) -> Line<BQuaternion> {
+        self.evaluator_expr(roll, |roll| single_axis_euler(EvaluatorExpression::Int(0), EvaluatorExpression::Int(0), *roll))
+    }
+
+    /// Unit vector pointing up in world space, away from gravity
+    ///
+    /// Ftd's world has a fixed up direction of +y and gravity always pulls along -y, so this is a constant
+    /// evaluator with no inputs rather than a sensor. Negate it for the direction of gravity.
+    /// Our craft's up direction is always this rotated by its orientation, see [`rotation_up`](Self::rotation_up),
+    /// and it is only equal to this when the craft is level.
+    pub fn world_up(&self) -> Line<BVector3> {
+        let mut eval = Evaluator::default();
+        eval.add_output(EvaluatorExpression::Vector(
+            Box::new(EvaluatorExpression::Int(0)),
+            Box::new(EvaluatorExpression::Int(1)),
+            Box::new(EvaluatorExpression::Int(0)),
+        ));
+
+        self.insert_component_with_output(eval)
+    }
+
+    /// A vector perpendicular to `v`, which is not normalized
+    ///
+    /// This is the cross product of `v` with the x axis if `|v.x| < |v.y|`, and with the y axis otherwise.
+    /// The chosen axis is never parallel to a nonzero `v`, so the output is only zero if `v` is zero.
+    /// Its length is between `|v| / sqrt(2)` and `|v|`.
+    pub fn any_perpendicular(&self, v: Line<BVector3>) -> Line<BVector3> {
+        self.evaluator_expr(v, |v| {
+            let axis = |x, y| EvaluatorExpression::Vector(
+                Box::new(EvaluatorExpression::Int(x)),
+                Box::new(EvaluatorExpression::Int(y)),
+                Box::new(EvaluatorExpression::Int(0)),
+            );
+
+            EvaluatorExpression::if_else(
+                EvaluatorExpression::Lt(
+                    Box::new(EvaluatorExpression::Abs(Box::new(EvaluatorExpression::GetX(v.clone())))),
+                    Box::new(EvaluatorExpression::Abs(Box::new(EvaluatorExpression::GetY(v.clone())))),
+                ),
+                EvaluatorExpression::Cross(v.clone(), Box::new(axis(1, 0))),
+                EvaluatorExpression::Cross(v, Box::new(axis(0, 1))),
+            )
+        })
+    }
+
+    /// Outputs 1 if every value of `values` is less than or equal to the next one, and 0 otherwise
+    ///
+    /// All the comparisons are done by one evaluator. A group of 0 or 1 values is always in order, so it outputs 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any value is from a different breadboard, or there are more than 5 distinct lines.
+    pub fn ordered<T: InputGroup<BNumber> + ?Sized>(&self, values: &T) -> Line<BNumber> {
+        let mut eval = Evaluator::default();
+        let values: Vec<_> = values.iter_lines()
+            .map(|line| {
+                let [value] = self.evaluator_inputs(&mut eval, [line]);
+                value
+            })
+            .collect();
+
+        let ordered = values.windows(2)
+            .map(|pair| EvaluatorExpression::Lte(Box::new(pair[0].clone()), Box::new(pair[1].clone())))
+            .reduce(|all, pair_ordered| EvaluatorExpression::OpAnd(Box::new(all), Box::new(pair_ordered)))
+            .unwrap_or(EvaluatorExpression::Int(1));
+        eval.add_output(ordered);
+
+        self.insert_component_with_output(eval)
+    }
+
+    /// Hyperbolic tangent of `x`, which smoothly goes from -1 to 1
+    pub fn tanh(&self, x: Line<BNumber>) -> Line<BNumber> {
+        self.evaluator_expr(x, |x| {
+            let exp_2x = EvaluatorExpression::Exp(Box::new(EvaluatorExpression::Int(2) * clamp_activation_input(*x)));
+            (exp_2x.clone() - EvaluatorExpression::Int(1)) / (exp_2x + EvaluatorExpression::Int(1))
+        })
+    }
+
+    /// Logistic sigmoid of `x`, which smoothly goes from 0 to 1 and is 0.5 at 0
+    pub fn sigmoid(&self, x: Line<BNumber>) -> Line<BNumber> {
+        self.evaluator_expr(x, |x| {
+            let exp_neg_x = EvaluatorExpression::Exp(Box::new(-clamp_activation_input(*x)));
+            EvaluatorExpression::Int(1) / (EvaluatorExpression::Int(1) + exp_neg_x)
+        })
+    }
+
+    /// Outputs 1 if `x` is a finite number, and 0 if it is NaN or infinite
+    pub fn is_finite(&self, x: Line<BNumber>) -> Line<BNumber> {
+        self.evaluator_expr(x, |x| finite_check(*x))
+    }
+
+    /// Outputs `x` if it is a finite number, and `fallback` if it is NaN or infinite
+    pub fn finite_or(&self, x: Line<BNumber>, fallback: Line<BNumber>) -> Line<BNumber> {
+        self.evaluator_expr2(x, fallback, |x, fallback| {
+            EvaluatorExpression::if_else(finite_check((*x).clone()), *x, *fallback)
+        })
+    }
+
+    /// Outputs `a / b`, or `fallback` if `b` is about 0
+    ///
+    /// `b` counts as 0 if its magnitude is at most 1e-6. Use [`div`](Self::div) if `b` is never 0,
+    /// since the guard makes the expression more expensive.
+    pub fn div_safe(&self, a: Line<BNumber>, b: Line<BNumber>, fallback: f32) -> Line<BNumber> {
+        self.guarded_division(a, b, fallback, |a, b| a / b)
+    }
+
+    /// Outputs `a % b`, or `fallback` if `b` is about 0, see [`div_safe`](Self::div_safe)
+    pub fn mod_safe(&self, a: Line<BNumber>, b: Line<BNumber>, fallback: f32) -> Line<BNumber> {
+        self.guarded_division(a, b, fallback, |a, b| a % b)
+    }
+
+    fn guarded_division(
+        &self,
+        a: Line<BNumber>,
+        b: Line<BNumber>,
+        fallback: f32,
+        division_fn: fn(EvaluatorExpression, EvaluatorExpression) -> EvaluatorExpression,
+    ) -> Line<BNumber> {
+        self.evaluator_expr2(a, b, |a, b| {
+            EvaluatorExpression::if_else(
+                EvaluatorExpression::Gt(
+                    Box::new(EvaluatorExpression::Abs(b.clone())),
+                    Box::new(EvaluatorExpression::Float(DIVISION_EPSILON)),
+                ),
+                division_fn(*a, *b),
+                EvaluatorExpression::Float(fallback.into()),
+            )
+        })
+    }
+
+    /// Outputs 1 if the distance between `a` and `b` is at most `epsilon`, and 0 otherwise
+    ///
+    /// Negative epsilons are treated as 0, which only outputs 1 when the vectors are exactly equal.
+    pub fn vec_approx_eq(&self, a: Line<BVector3>, b: Line<BVector3>, epsilon: f32) -> Line<BNumber> {
+        let epsilon = epsilon.max(0.0);
+
+        self.evaluator_expr2(a, b, |a, b| {
+            EvaluatorExpression::Lte(
+                Box::new(EvaluatorExpression::Magnitude(Box::new(*a - *b))),
+                Box::new(EvaluatorExpression::Float(epsilon.into())),
+            )
+        })
+    }
+
+    /// Scales `v` down so its length is at most `max`, keeping its direction
+    ///
+    /// Vectors no longer than `max` are output unchanged. Negative maximums are treated as 0,
+    /// which outputs the zero vector.
+    pub fn clamp_magnitude(&self, v: Line<BVector3>, max: f32) -> Line<BVector3> {
+        let max = EvaluatorExpression::Float(max.max(0.0).into());
+
+        self.evaluator_expr(v, |v| {
+            let magnitude = EvaluatorExpression::Magnitude(v.clone());
+
+            // a zero length vector is never longer than max, so this never divides by 0
+            EvaluatorExpression::if_else(
+                EvaluatorExpression::Gt(Box::new(magnitude.clone()), Box::new(max.clone())),
+                *v.clone() * (max / magnitude),
+                *v,
+            )
+        })
+    }
+
+    /// Limits `x` to the range `[0, 1]`, see [`clamp`](Self::clamp)
+    pub fn clamp01(&self, x: Line<BNumber>) -> Line<BNumber> {
+        self.evaluator_expr(x, |x| EvaluatorExpression::clamp(*x, EvaluatorExpression::Int(0), EvaluatorExpression::Int(1)))
+    }
+
+    /// Limits `x` to the range `[-1, 1]`, see [`clamp`](Self::clamp)
+    pub fn clamp_unit(&self, x: Line<BNumber>) -> Line<BNumber> {
+        self.evaluator_expr(x, |x| EvaluatorExpression::clamp(*x, EvaluatorExpression::Int(-1), EvaluatorExpression::Int(1)))
+    }
+
+    /// Distance from `point` to the plane through `plane_point` with normal `plane_normal`
+    ///
+    /// The distance is positive on the side `plane_normal` points to and negative on the other side.
+    /// `plane_normal` does not need to be normalized, but if it is zero there is no plane and 0 is output.
+    pub fn signed_distance_to_plane(
+        &self,
+        point: Line<BVector3>,
+        plane_point: Line<BVector3>,
+        plane_normal: Line<BVector3>,
+    ) -> Line<BNumber> {
+        self.evaluator_expr3(point, plane_point, plane_normal, |point, plane_point, plane_normal| {
+            let normal_length = EvaluatorExpression::Magnitude(plane_normal.clone());
+
+            EvaluatorExpression::if_else(
+                EvaluatorExpression::Gt(Box::new(normal_length.clone()), Box::new(EvaluatorExpression::Int(0))),
+                (*point - *plane_point) * *plane_normal / normal_length,
+                EvaluatorExpression::Int(0),
+            )
+        })
+    }
+}
+
+fn clamp_activation_input(x: EvaluatorExpression) -> EvaluatorExpression {
+    EvaluatorExpression::clamp(
+        x,
+        EvaluatorExpression::Float(-ACTIVATION_INPUT_LIMIT),
+        EvaluatorExpression::Float(ACTIVATION_INPUT_LIMIT),
+    )
+}
+
+/// Expression which is 1 if `x` is finite
+///
+/// `x - x` is 0 for any finite number, but NaN for NaN and infinity,
+/// and NaN compares unequal to everything in ftd's float comparisons.
+fn finite_check(x: EvaluatorExpression) -> EvaluatorExpression {
+    EvaluatorExpression::Eq(
+        Box::new(x.clone() - x),
+        Box::new(EvaluatorExpression::Int(0)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::breadboard::{AltitudeOutputType, BVector3, Breadboard, Line, Simulation, VelocityOutputType, COMPONENT_ID_START};
+    use crate::ftd_data::DataEntry;
+
+    /// Outputs of `clamp_magnitude` with a max of 2 for each vector
+    fn clamp_magnitude_outputs(vectors: &[[f64; 3]]) -> Vec<[f64; 3]> {
+        let breadboard = Breadboard::new();
+        let position = breadboard.position();
+        let clamped = breadboard.clamp_magnitude(position.clone(), 2.0);
+
+        let mut simulation = Simulation::new(&breadboard);
+        vectors.iter()
+            .map(|vector| {
+                simulation.set_vector(&position, *vector);
+                simulation.step().unwrap();
+                simulation.vector(&clamped).unwrap()
+            })
+            .collect()
+    }
+
+    /// Expression string of the evaluator at `component_index`
+    fn expression_string(breadboard: &Breadboard, component_index: u32) -> String {
+        let block = breadboard.block_data().unwrap();
+        let section = block.section_data((COMPONENT_ID_START + component_index).into()).unwrap();
+        let Some(DataEntry::String(expr_string)) = section.entry(0) else {
+            panic!("evaluator has no expression string");
+        };
+
+        expr_string.clone()
+    }
+
+    #[test]
+    fn clamp_magnitude_expression() {
+        let breadboard = Breadboard::new();
+        let _ = breadboard.clamp_magnitude(breadboard.position(), 2.0);
+
+        assert_eq!(
+            expression_string(&breadboard, 1),
+            "If(((a).magnitude) > (2.0), (a) * ((2.0) / ((a).magnitude)), a)",
+        );
+    }
+
+    #[test]
+    fn clamp01_and_clamp_unit_expressions() {
+        let breadboard = Breadboard::new();
+        let altitude = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let _ = breadboard.clamp01(altitude.clone());
+        let _ = breadboard.clamp_unit(altitude);
+
+        assert_eq!(expression_string(&breadboard, 1), "Min(Max(a, 0), 1)");
+        assert_eq!(expression_string(&breadboard, 2), "Min(Max(a, -1), 1)");
+    }
+
+    #[test]
+    fn remap_expressions() {
+        let breadboard = Breadboard::new();
+        let altitude = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let [in_lo, in_hi, out_lo, out_hi] = [0.0, 100.0, -1.0, 1.0].map(|value| breadboard.constant(value));
+        let _ = breadboard.remap(altitude.clone(), in_lo.clone(), in_hi.clone(), out_lo.clone(), out_hi.clone(), false);
+        let _ = breadboard.remap(altitude, in_lo, in_hi, out_lo, out_hi, true);
+
+        assert_eq!(expression_string(&breadboard, 5), "If((c) != (b), (d) + ((((a) - (b)) * ((e) - (d))) / ((c) - (b))), d)");
+        assert_eq!(
+            expression_string(&breadboard, 6),
+            "If((c) != (b), Min(Max((d) + ((((a) - (b)) * ((e) - (d))) / ((c) - (b))), Min(d, e)), Max(d, e)), d)",
+        );
+    }
+
+    #[test]
+    fn remap_clamped_values() {
+        let breadboard = Breadboard::new();
+        let altitude = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let [in_lo, in_hi, out_lo, out_hi] = [0.0, 100.0, 1.0, -1.0].map(|value| breadboard.constant(value));
+        let mapped = breadboard.remap(altitude.clone(), in_lo.clone(), in_hi.clone(), out_lo.clone(), out_hi.clone(), false);
+        let clamped = breadboard.remap(altitude.clone(), in_lo, in_hi, out_lo, out_hi, true);
+
+        let mut simulation = Simulation::new(&breadboard);
+        let mut outputs = Vec::new();
+        for altitude_value in [25.0, 150.0, -50.0] {
+            simulation.set_number(&altitude, altitude_value);
+            simulation.step().unwrap();
+            outputs.push((simulation.number(&mapped).unwrap(), simulation.number(&clamped).unwrap()));
+        }
+
+        // the output range is reversed, so clamping has to order its bounds
+        assert_eq!(outputs, [(0.5, 0.5), (-2.0, -1.0), (2.0, 1.0)]);
+    }
+
+    #[test]
+    fn intercept_point_values() {
+        let breadboard = Breadboard::new();
+        let target_pos = breadboard.position();
+        let target_vel = breadboard.velocity(VelocityOutputType::Magnitude);
+        let intercept = breadboard.intercept_point(target_pos.clone(), target_vel.clone(), breadboard.constant(50.0));
+        // position component inserted by intercept_point for our own position
+        let our_pos: Line<BVector3> = Line::new(breadboard.clone(), 3, 0);
+
+        let mut simulation = Simulation::new(&breadboard);
+        simulation.set_vector(&our_pos, [0.0, 0.0, 0.0]);
+        simulation.set_vector(&target_pos, [100.0, 0.0, 0.0]);
+
+        let mut intercepts = Vec::new();
+        // stationary, moving sideways, and moving away faster than the projectile
+        for velocity in [[0.0, 0.0, 0.0], [0.0, 30.0, 0.0], [60.0, 0.0, 0.0]] {
+            simulation.set_vector(&target_vel, velocity);
+            simulation.step().unwrap();
+            intercepts.push(simulation.vector(&intercept).unwrap());
+        }
+
+        assert_eq!(intercepts, [[100.0, 0.0, 0.0], [100.0, 75.0, 0.0], [100.0, 0.0, 0.0]]);
+    }
+
+    #[test]
+    fn closing_speed_sign() {
+        let breadboard = Breadboard::new();
+        let target = breadboard.target_info();
+        let closing_speed = breadboard.closing_speed_to_target(&target);
+        // position and velocity components inserted by closing_speed_to_target for our own motion
+        let our_pos: Line<BVector3> = Line::new(breadboard.clone(), 1, 0);
+        let our_vel: Line<BVector3> = Line::new(breadboard.clone(), 2, 0);
+
+        let mut simulation = Simulation::new(&breadboard);
+        simulation.set_vector(&target.position, [100.0, 0.0, 0.0]);
+        simulation.set_vector(&our_pos, [0.0, 0.0, 0.0]);
+
+        let mut speeds = Vec::new();
+        // target approaching, target receding, us approaching, target moving sideways, and no target
+        for (present, target_vel, our_vel_value) in [
+            (1.0, [-10.0, 0.0, 0.0], [0.0, 0.0, 0.0]),
+            (1.0, [10.0, 0.0, 0.0], [0.0, 0.0, 0.0]),
+            (1.0, [0.0, 0.0, 0.0], [5.0, 0.0, 0.0]),
+            (1.0, [0.0, 10.0, 0.0], [0.0, 0.0, 0.0]),
+            (0.0, [-10.0, 0.0, 0.0], [0.0, 0.0, 0.0]),
+        ] {
+            simulation.set_number(&target.present, present);
+            simulation.set_vector(&target.velocity, target_vel);
+            simulation.set_vector(&our_vel, our_vel_value);
+            simulation.step().unwrap();
+            speeds.push(simulation.number(&closing_speed).unwrap());
+        }
+
+        assert_eq!(speeds, [10.0, -10.0, 5.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn finite_expressions() {
+        let breadboard = Breadboard::new();
+        let altitude = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let _ = breadboard.is_finite(altitude.clone());
+        let _ = breadboard.finite_or(altitude, breadboard.constant(5.0));
+
+        assert_eq!(expression_string(&breadboard, 1), "((a) - (a)) = (0)");
+        assert_eq!(expression_string(&breadboard, 3), "If(((a) - (a)) = (0), a, b)");
+    }
+
+    #[test]
+    fn finite_values() {
+        let breadboard = Breadboard::new();
+        let altitude = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let finite = breadboard.is_finite(altitude.clone());
+        let guarded = breadboard.finite_or(altitude.clone(), breadboard.constant(5.0));
+
+        let mut simulation = Simulation::new(&breadboard);
+        let mut outputs = Vec::new();
+        for altitude_value in [-2.5, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            simulation.set_number(&altitude, altitude_value);
+            simulation.step().unwrap();
+            outputs.push((simulation.number(&finite).unwrap(), simulation.number(&guarded).unwrap()));
+        }
+
+        assert_eq!(outputs, [(1.0, -2.5), (0.0, 5.0), (0.0, 5.0), (0.0, 5.0)]);
+    }
+
+    #[test]
+    fn activation_expressions() {
+        let breadboard = Breadboard::new();
+        let altitude = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let _ = breadboard.tanh(altitude.clone());
+        let _ = breadboard.sigmoid(altitude);
+
+        assert_eq!(expression_string(&breadboard, 1), "((Exp((2) * (Min(Max(a, -20.0), 20.0)))) - (1)) / ((Exp((2) * (Min(Max(a, -20.0), 20.0)))) + (1))");
+        assert_eq!(expression_string(&breadboard, 2), "(1) / ((1) + (Exp(-(Min(Max(a, -20.0), 20.0)))))");
+    }
+
+    #[test]
+    fn single_axis_rotation_expressions() {
+        let breadboard = Breadboard::new();
+        let angle = breadboard.constant(30.0);
+        let _ = breadboard.yaw_rotation(angle.clone());
+        let _ = breadboard.pitch_rotation(angle.clone());
+        let _ = breadboard.roll_rotation(angle);
+
+        assert_eq!(expression_string(&breadboard, 1), "FromEuler(0, a, 0)");
+        assert_eq!(expression_string(&breadboard, 2), "FromEuler(a, 0, 0)");
+        assert_eq!(expression_string(&breadboard, 3), "FromEuler(0, 0, a)");
+    }
+
+    #[test]
+    fn rotation_axes() {
+        let breadboard = Breadboard::new();
+        let rotation = breadboard.yaw_rotation(breadboard.altitude(AltitudeOutputType::SeaLevel));
+        let forward = rotation.forward();
+        let up = rotation.up();
+        let right = rotation.right();
+        let position = breadboard.position();
+        let _ = breadboard.rotate(position.clone(), rotation);
+
+        assert_eq!(expression_string(&breadboard, 2), "(Vector(0, 0, 1)) * (a)");
+        assert_eq!(expression_string(&breadboard, 3), "(Vector(0, 1, 0)) * (a)");
+        assert_eq!(expression_string(&breadboard, 4), "(Vector(1, 0, 0)) * (a)");
+        assert_eq!(expression_string(&breadboard, 6), "(a) * (b)");
+
+        let mut simulation = Simulation::new(&breadboard);
+        simulation.set_number(&Line::new(breadboard.clone(), 0, 0), 90.0);
+        simulation.set_vector(&position, [0.0, 0.0, 0.0]);
+        simulation.step().unwrap();
+        for (axis, expected) in [(forward, [1.0, 0.0, 0.0]), (up, [0.0, 1.0, 0.0]), (right, [0.0, 0.0, -1.0])] {
+            for (a, b) in simulation.vector(&axis).unwrap().into_iter().zip(expected) {
+                assert!((a - b).abs() < 1e-9, "{a} != {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn look_rotation_expression() {
+        let breadboard = Breadboard::new();
+        let _ = breadboard.look_rotation(breadboard.position(), breadboard.velocity(VelocityOutputType::Magnitude));
+
+        assert_eq!(
+            expression_string(&breadboard, 2),
+            "FromEuler(Atan(-((a).y), Sqrt((((a).x) * ((a).x)) + (((a).z) * ((a).z)))), Atan((a).x, (a).z), If(((((b) * (Vector(Cos(Atan((a).x, (a).z)), 0, -(Sin(Atan((a).x, (a).z)))))) * ((b) * (Vector(Cos(Atan((a).x, (a).z)), 0, -(Sin(Atan((a).x, (a).z))))))) + (((b) * (((a) / ((a).magnitude)) x (Vector(Cos(Atan((a).x, (a).z)), 0, -(Sin(Atan((a).x, (a).z))))))) * ((b) * (((a) / ((a).magnitude)) x (Vector(Cos(Atan((a).x, (a).z)), 0, -(Sin(Atan((a).x, (a).z))))))))) > ((0.000001) * ((b).sqrMagnitude)), Atan(-((b) * (Vector(Cos(Atan((a).x, (a).z)), 0, -(Sin(Atan((a).x, (a).z)))))), (b) * (((a) / ((a).magnitude)) x (Vector(Cos(Atan((a).x, (a).z)), 0, -(Sin(Atan((a).x, (a).z))))))), 0))",
+        );
+    }
+
+    #[test]
+    fn look_rotation_parallel_up_has_no_roll() {
+        let breadboard = Breadboard::new();
+        let forward = breadboard.position();
+        let up = breadboard.velocity(VelocityOutputType::Magnitude);
+        let rotation = breadboard.look_rotation(forward.clone(), up.clone());
+
+        let mut simulation = Simulation::new(&breadboard);
+        let mut rotation_with_up = |up_value| {
+            simulation.set_vector(&forward, [1.0, 1.0, 0.0]);
+            simulation.set_vector(&up, up_value);
+            simulation.step().unwrap();
+            simulation.rotation(&rotation).unwrap()
+        };
+
+        let level = rotation_with_up([0.0, 1.0, 0.0]);
+        for parallel_up in [[2.0, 2.0, 0.0], [-1.0, -1.0, 0.0], [0.0, 0.0, 0.0]] {
+            let fallback = rotation_with_up(parallel_up);
+            for (a, b) in fallback.iter().zip(level) {
+                assert!((a - b).abs() < 1e-9, "{fallback:?} != {level:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn world_up_is_constant() {
+        let breadboard = Breadboard::new();
+        let up = breadboard.world_up();
+
+        assert_eq!(expression_string(&breadboard, 0), "Vector(0, 1, 0)");
+
+        let mut simulation = Simulation::new(&breadboard);
+        simulation.step().unwrap();
+        assert_eq!(simulation.vector(&up).unwrap(), [0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn any_perpendicular_expression() {
+        let breadboard = Breadboard::new();
+        let _ = breadboard.position().any_perpendicular();
+
+        assert_eq!(
+            expression_string(&breadboard, 1),
+            "If((Abs((a).x)) < (Abs((a).y)), (a) x (Vector(1, 0, 0)), (a) x (Vector(0, 1, 0)))",
+        );
+    }
+
+    #[test]
+    fn ordered_expression() {
+        let breadboard = Breadboard::new();
+        let a = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let b = breadboard.constant(1.0);
+        let _ = breadboard.ordered(&[a.clone(), b, a.clone()][..]);
+        let _ = breadboard.ordered(&a);
+
+        assert_eq!(expression_string(&breadboard, 2), "((a) <= (b)) & ((b) <= (a))");
+        assert_eq!(expression_string(&breadboard, 3), "1");
+    }
+
+    #[test]
+    fn safe_division_expressions() {
+        let breadboard = Breadboard::new();
+        let a = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let b = breadboard.altitude(AltitudeOutputType::TerrainLevel);
+        let _ = breadboard.div_safe(a.clone(), b.clone(), 0.0);
+        let _ = breadboard.mod_safe(a, b, -1.0);
+
+        assert_eq!(expression_string(&breadboard, 2), "If((Abs(b)) > (0.000001), (a) / (b), 0.0)");
+        assert_eq!(expression_string(&breadboard, 3), "If((Abs(b)) > (0.000001), (a) % (b), -1.0)");
+    }
+
+    #[test]
+    fn heading_of_expression() {
+        let breadboard = Breadboard::new();
+        let _ = breadboard.heading_of(breadboard.position());
+
+        assert_eq!(expression_string(&breadboard, 1), "((Atan((a).x, (a).z)) + (360)) % (360)");
+    }
+
+    #[test]
+    fn angle_diff_and_normalize_angle_expressions() {
+        let breadboard = Breadboard::new();
+        let a = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let b = breadboard.altitude(AltitudeOutputType::WaveLevel);
+        let _ = breadboard.angle_diff(a.clone(), b);
+        let _ = breadboard.normalize_angle(a);
+
+        assert_eq!(expression_string(&breadboard, 2), "((((((a) - (b)) + (180)) % (360)) + (360)) % (360)) - (180)");
+        assert_eq!(expression_string(&breadboard, 3), "(((a) % (360)) + (360)) % (360)");
+    }
+
+    #[test]
+    fn angle_diff_and_normalize_angle_values() {
+        let breadboard = Breadboard::new();
+        let a = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let b = breadboard.altitude(AltitudeOutputType::WaveLevel);
+        let diff = breadboard.angle_diff(a.clone(), b.clone());
+        let normalized = breadboard.normalize_angle(a.clone());
+
+        let mut simulation = Simulation::new(&breadboard);
+        let mut outputs = Vec::new();
+        for (a_value, b_value) in [(350.0, 10.0), (10.0, 350.0), (180.0, 0.0), (0.0, 180.0), (-540.0, 0.0), (720.0, 0.0)] {
+            simulation.set_number(&a, a_value);
+            simulation.set_number(&b, b_value);
+            simulation.step().unwrap();
+            outputs.push((simulation.number(&diff).unwrap(), simulation.number(&normalized).unwrap()));
+        }
+
+        assert_eq!(outputs, [(-20.0, 350.0), (20.0, 10.0), (-180.0, 180.0), (-180.0, 0.0), (-180.0, 180.0), (0.0, 0.0)]);
+    }
+
+    #[test]
+    fn lerp_angle_expression() {
+        let breadboard = Breadboard::new();
+        let _ = breadboard.lerp_angle(breadboard.constant(350.0), breadboard.constant(10.0), breadboard.constant(0.5));
+
+        assert_eq!(expression_string(&breadboard, 3), "((((a) + ((((((((b) - (a)) + (180)) % (360)) + (360)) % (360)) - (180)) * (c))) % (360)) + (360)) % (360)");
+    }
+
+    #[test]
+    fn lerp_angle_values() {
+        let breadboard = Breadboard::new();
+        let a = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let b = breadboard.altitude(AltitudeOutputType::WaveLevel);
+        let angle = breadboard.lerp_angle(a.clone(), b.clone(), breadboard.constant(0.25));
+
+        let mut simulation = Simulation::new(&breadboard);
+        let mut angles = Vec::new();
+        for (a_value, b_value) in [(350.0, 10.0), (10.0, 350.0), (-90.0, 90.0), (720.0, 40.0)] {
+            simulation.set_number(&a, a_value);
+            simulation.set_number(&b, b_value);
+            simulation.step().unwrap();
+            angles.push(simulation.number(&angle).unwrap());
+        }
+
+        assert_eq!(angles, [355.0, 5.0, 225.0, 10.0]);
+    }
+
+    #[test]
+    fn bearing_and_elevation_values() {
+        let breadboard = Breadboard::new();
+        let point = breadboard.position();
+        let yaw = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let rotation = breadboard.yaw_rotation(yaw.clone());
+        let bearing = breadboard.bearing_to(point.clone(), rotation.clone());
+        let elevation = breadboard.elevation_to(point.clone(), rotation);
+
+        let mut simulation = Simulation::new(&breadboard);
+        let mut angles = Vec::new();
+        // facing east, so forward is +x and right is -z
+        for offset in [[5.0, 0.0, 0.0], [0.0, 0.0, -5.0], [5.0, 5.0, 0.0]] {
+            simulation.set_number(&yaw, 90.0);
+            for our_position in [Line::new(breadboard.clone(), 3, 0), Line::new(breadboard.clone(), 5, 0)] {
+                simulation.set_vector(&our_position, [10.0, 2.0, 10.0]);
+            }
+            simulation.set_vector(&point, [10.0 + offset[0], 2.0 + offset[1], 10.0 + offset[2]]);
+            simulation.step().unwrap();
+            angles.push([simulation.number(&bearing).unwrap(), simulation.number(&elevation).unwrap()]);
+        }
+
+        for (angle, expected) in angles.iter().flatten().zip([0.0, 0.0, 90.0, 0.0, 0.0, 45.0]) {
+            assert!((angle - expected).abs() < 1e-9, "{angles:?}");
+        }
+    }
+
+    #[test]
+    fn heading_of_values() {
+        let breadboard = Breadboard::new();
+        let direction = breadboard.position();
+        let heading = breadboard.heading_of(direction.clone());
+
+        let mut simulation = Simulation::new(&breadboard);
+        let mut headings = Vec::new();
+        for vector in [[0.0, 5.0, 2.0], [3.0, 0.0, 0.0], [0.0, 0.0, -1.0], [-1.0, -1.0, 0.0]] {
+            simulation.set_vector(&direction, vector);
+            simulation.step().unwrap();
+            headings.push(simulation.number(&heading).unwrap());
+        }
+
+        assert_eq!(headings, [0.0, 90.0, 180.0, 270.0]);
+    }
+
+    #[test]
+    fn clamp_magnitude_values() {
+        assert_eq!(
+            clamp_magnitude_outputs(&[[0.0, 0.0, -4.0], [1.0, 0.0, 1.0], [0.0, 0.0, 0.0]]),
+            [[0.0, 0.0, -2.0], [1.0, 0.0, 1.0], [0.0, 0.0, 0.0]],
+        );
+    }
+
+    #[test]
+    fn signed_distance_to_plane_expression() {
+        let breadboard = Breadboard::new();
+        let position = breadboard.position();
+        let target = breadboard.target_info();
+        let _ = breadboard.signed_distance_to_plane(position, target.position, target.velocity);
+
+        assert_eq!(
+            expression_string(&breadboard, 2),
+            "If(((c).magnitude) > (0), (((a) - (b)) * (c)) / ((c).magnitude), 0)",
+        );
+    }
+}
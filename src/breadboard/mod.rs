@@ -1,24 +1,157 @@
+mod component_uuid;
+mod error;
 mod evaluator;
+mod expr_type;
+mod import;
 mod line_value;
+mod math;
+mod parse;
+mod simulate;
+mod stateful;
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::fs::File;
 use std::path::Path;
-use std::io;
+use std::io::{self, BufWriter, Write};
 use std::rc::Rc;
 
-use uuid::{Uuid, uuid};
+use uuid::Uuid;
 
+pub use error::{BreadboardError, ImportError, SimulationError, ValidationError};
+pub use evaluator::EvaluatorExpression;
+pub use expr_type::{ExpressionTypeError, ValueType};
 pub use line_value::*;
+pub use parse::ParseError;
+pub use simulate::{SimValue, Simulation};
+pub use stateful::{BuiltEvaluator, EvaluatorBuilder, EvaluatorOutput, EvaluatorOutputGroup, FeedbackValue, PidGains, StateTransition, MAX_MOVING_AVERAGE_WINDOW, TICK_DELTA_TIME};
+use component_uuid::*;
 use crate::find_prefabs_folder;
-use crate::ftd_data::{ftd_uuid_to_uuid, BlockData, BlueprintData, DataEntry, SectionData, SectionId, Vector2};
+use crate::ftd_data::{ftd_uuid_to_uuid, BlockData, BlueprintData, DataEntry, DataEntryId, SectionData, SectionId, Vector2, MAX_SECTIONS};
 
 /// Ids used for components to avoid interfering with other ids
 const COMPONENT_ID_START: u32 = 72542;
 
+/// Section of the breadboard block listing its components, which the breadboard looks at to instantiate them
+///
+/// Each component has a uuid entry and an entry with the id of its own section, at entry ids `2 * i` and `2 * i + 1`.
+const MAIN_SECTION_ID: u32 = 9999;
+
+/// Sections which ftd saves empty in every breadboard block
+///
+/// It is not known what these are for, they were copied from a blueprint saved by ftd 3.8.0.4, the `GameVersion`
+/// in the blueprint json. They may hold data of a feature the breadboard doesn't use, so other versions may save
+/// them differently. 72541 is right before [`COMPONENT_ID_START`], which may not be a coincidence.
+/// They are saved anyway in case ftd needs them to load the block.
+const EMPTY_SECTION_IDS: [u32; 2] = [3000, 72541];
+
+/// Blueprint file json before the material cost
+const BLUEPRINT_FILE_START: &str = r#"{"FileModelVersion":{"Major":1,"Minor":0},"Name":"TEST_BREADBOARD","Version":0,"SavedTotalBlockCount":1,"SavedMaterialCost":"#;
+
+/// Blueprint file json between the material cost and the `CSI` array
+///
+/// The contained material costs are for material stored in containers, which a breadboard does not have.
+const BLUEPRINT_FILE_BEFORE_CSI: &str = r#","ContainedMaterialCost":0.0,"ItemDictionary":{"227":"5ef97d26-1196-4b1a-ba1d-fd539c26b684","0":"75a78e48-0848-45ee-9df2-e2b328c1933d"},"Blueprint":{"ContainedMaterialCost":0.0,"CSI":["#;
+
+/// Blueprint file json between the `CSI` array and the block data string
+const BLUEPRINT_FILE_MIDDLE: &str = r#"],"COL":null,"SCs":[],"BLP":["0,0,0"],"BLR":[0],"BP1":null,"BP2":null,"BCI":[0],"BEI":null,"BlockData":""#;
+
+/// Material cost of a single breadboard block
+///
+/// This is the `SavedMaterialCost` ftd wrote in the blueprint file the json around the block data was taken from,
+/// which only contains one breadboard block.
+pub const BREADBOARD_MATERIAL_COST: f32 = 10.0;
+
+/// Number of values in the `CSI` array of the blueprint json
+///
+/// The array holds per construct info which is not documented by ftd, unset values are saved as [`CSI_UNSET`].
+/// A construct containing only a breadboard leaves every value unset, and the values don't affect where
+/// the breadboard is placed, so they are not configurable. Ftd saves exactly this many values.
+const CSI_LEN: usize = 80;
+
+/// Value ftd saves in the `CSI` array for info which is not set
+const CSI_UNSET: f32 = -1.0;
+
+/// Blueprint file json between the block data string and the `SerialisedInfo` object
+const BLUEPRINT_FILE_AFTER_DATA: &str = r#"","VehicleData":"sct0AAAAAAAA","designChanged":false,"blueprintVersion":0,"blueprintName":"TEST_BREADBOARD","SerialisedInfo":"#;
+
+/// Blueprint file json between the `SerialisedInfo` object and the item number
+const BLUEPRINT_FILE_BEFORE_ITEM_NUMBER: &str = r#","Name":null,"ItemNumber":"#;
+
+/// Blueprint file json between the local rotation and the persistent sub object index
+const BLUEPRINT_FILE_BEFORE_PERSISTENT_INDICES: &str = r#"","ForceId":0,"TotalBlockCount":1,"MaxCords":"1,1,1","MinCords":"0,0,0","BlockIds":[227],"BlockState":null,"AliveCount":1,"BlockStringData":null,"BlockStringDataIds":null,"GameVersion":"3.8.0.4","PersistentSubObjectIndex":"#;
+
+/// Value ftd saves for the persistent indices of a construct which is not attached to another construct
+pub const PERSISTENT_INDEX_UNSET: i32 = -1;
+
+/// Blueprint file json after the persistent block index
+const BLUEPRINT_FILE_END: &str = r#","AuthorDetails":{"Valid":true,"ForeignBlocks":0,"CreatorId":"0ab41fc3-fd53-4843-becf-7608b7c315b7","ObjectId":"5bb43b25-8e79-4e92-9db3-076b363114a7","CreatorReadableName":"DeltaForce","HashV1":"6831413c85b3e408740dc00f5580382c"},"BlockCount":1}}"#;
+
+/// Maximum number of components which can be saved in one breadboard
+///
+/// Each component has its own section in the breadboard block, next to the main section and the sections ftd
+/// saves empty, and a block can have at most [`MAX_SECTIONS`] sections.
+/// Ftd may have a lower limit of its own, if so this should be lowered to match it.
+pub const MAX_COMPONENTS: usize = MAX_SECTIONS - EMPTY_SECTION_IDS.len() - 1;
+
+/// Number of inputs of one multiply component above which [`Breadboard::validate`] reports a warning
+///
+/// It is not known if ftd limits the inputs of a multiply component, this is only how many have been tried.
+/// Multiply components with more inputs are still saved.
+pub const MAX_MULTIPLY_INPUTS: usize = 16;
+
+/// Magnitude values of constant, random input and switch components are clamped to
+///
+/// Numeric options of components are clamped to the range their settings allow, and nan is saved as 0.
+/// Numbers written into evaluator expressions, like [`precise_constant`](Breadboard::precise_constant)
+/// and [`new_vector`](Breadboard::new_vector), are not clamped since the expression is parsed as text,
+/// so use those for values outside this range. Only non finite numbers are changed there,
+/// since they can't be written in an expression, see [`EvaluatorExpression::Float`].
+pub const COMPONENT_VALUE_LIMIT: f32 = 10000.0;
+
+/// Magnitude the multiplier of multiply components is clamped to, see [`COMPONENT_VALUE_LIMIT`]
+pub const MULTIPLIER_LIMIT: f32 = 100.0;
+
+/// Maximum length in bytes of the expression string saved for one evaluator, including the commas between outputs
+///
+/// Ftd's actual limit is not known, this is a conservative cap to avoid saving blueprints which ftd may fail to load.
+/// A longer evaluator has to be split into several evaluators by hand, since outputs which read back
+/// their own previous values can't be moved to another evaluator automatically.
+pub const MAX_EVALUATOR_EXPRESSION_LENGTH: usize = 1 << 16;
+
+/// Rough relative update cost of each component kind, keyed by ftd uuid
+///
+/// These are guesses based on how much work each component does, not values taken from ftd.
+const COMPONENT_COSTS: &[(Uuid, f32)] = &[
+    // constant
+    (CONSTANT_UUID, 0.5),
+    // random input
+    (RANDOM_INPUT_UUID, 1.0),
+    // altitude
+    (ALTITUDE_UUID, 1.0),
+    // position
+    (POSITION_UUID, 1.0),
+    // speed and velocity
+    (SPEED_UUID, 1.0),
+    // target info
+    (TARGET_INFO_UUID, 2.0),
+    // multiply
+    (MULTIPLY_UUID, 1.0),
+    // switch
+    (SWITCH_UUID, 1.0),
+    // evaluator
+    (EVALUATOR_UUID, 3.0),
+];
+
+/// Cost used for components missing from `COMPONENT_COSTS`
+const DEFAULT_COMPONENT_COST: f32 = 1.0;
+
 struct BreadboardWireMap {
     /// 2d array where first index is component id, 2nd is output index
     data: Vec<Vec<Uuid>>,
+    /// Seed of each component's wire uuids, if they are derived from its structure instead of being random
+    seeds: Vec<Option<u64>>,
 }
 
 impl BreadboardWireMap {
@@ -30,12 +163,16 @@ impl BreadboardWireMap {
 
         BreadboardWireMap {
             data,
+            seeds: vec![None; num_components],
         }
     }
 
-    fn set_num_outputs(&mut self, component_index: usize, num_outputs: usize) {
-        for _ in 0..num_outputs {
-            self.data[component_index].push(Uuid::new_v4());
+    /// If `seed` is set, the uuids of this component's wires are derived from it, so they are the same every time it is saved
+    fn set_num_outputs(&mut self, component_index: usize, num_outputs: usize, seed: Option<u64>) {
+        self.seeds[component_index] = seed;
+        for output_index in 0..num_outputs {
+            let uuid = wire_uuid(seed, b"output", output_index);
+            self.data[component_index].push(uuid);
         }
     }
 
@@ -43,13 +180,12 @@ impl BreadboardWireMap {
         &self.data[line.component_index][line.output_index]
     }
 
-    fn get_component_data_section_with_inputs_and_outputs(&mut self, component_index: usize, component: &dyn Component) -> SectionData {
-        self.set_num_outputs(component_index, component.num_outputs());
-
+    /// Should only be called after the outputs of every component are set, since inputs can come from any component
+    fn get_component_data_section_with_inputs_and_outputs(&self, component_index: usize, component: &dyn Component) -> SectionData {
         let mut input_bytes = Vec::new();
-        for line in component.inputs() {
+        for (input_index, line) in component.inputs().iter().enumerate() {
             // I don't really know what this uuid is for but ftd needs 2 uuids for input lines
-            input_bytes.extend_from_slice(Uuid::new_v4().as_bytes());
+            input_bytes.extend_from_slice(wire_uuid(self.seeds[component_index], b"input", input_index).as_bytes());
 
             input_bytes.extend_from_slice(self.get_output_uuid(*line).as_bytes());
         }
@@ -65,72 +201,248 @@ impl BreadboardWireMap {
     }
 }
 
+/// Returns a random uuid, or if `seed` is set one derived from `seed`, `kind` and `index`
+fn wire_uuid(seed: Option<u64>, kind: &[u8], index: usize) -> Uuid {
+    let Some(seed) = seed else {
+        return Uuid::new_v4();
+    };
+
+    let high = fnv1a(fnv1a(seed, kind), &(index as u64).to_le_bytes());
+    let low = fnv1a(high, &seed.to_le_bytes());
+
+    let mut bytes = [0; 16];
+    bytes[..8].copy_from_slice(&high.to_le_bytes());
+    bytes[8..].copy_from_slice(&low.to_le_bytes());
+    uuid::Builder::from_random_bytes(bytes).into_uuid()
+}
+
 static NEXT_BREADBOARD_ID: AtomicUsize = AtomicUsize::new(0);
 
+// These are only borrowed inside breadboard methods and never while calling user code.
+// Components only store `LineInner`, not a `Breadboard`, so component methods called
+// while the components are borrowed have no way to insert a new component.
 struct BreadboardInner {
     id: usize,
     components: RefCell<Vec<Box<dyn Component>>>,
+    /// Extra entries set by the user for a component, keyed by component index
+    section_overlays: RefCell<HashMap<usize, SectionData>>,
+    /// Lines marked as final outputs with [`Breadboard::mark_output`]
+    outputs: RefCell<Vec<LineInner>>,
+    /// If set, components not needed for any marked output are left out when saving
+    prune_unused: Cell<bool>,
+    /// If set, components are saved sorted by their structure instead of in insertion order
+    canonical_order: Cell<bool>,
+    /// Entries of the blueprint's `SerialisedInfo` dictionary, set with [`Breadboard::set_serialised_info`]
+    serialised_info: RefCell<BTreeMap<String, String>>,
+    /// Material cost saved in the blueprint file, set with [`Breadboard::set_material_cost`]
+    material_cost: Cell<f32>,
+    /// Position saved in the blueprint file, set with [`Breadboard::set_local_position`]
+    local_position: Cell<[f32; 3]>,
+    /// Rotation quaternion saved in the blueprint file as x, y, z, w, set with [`Breadboard::set_local_rotation`]
+    local_rotation: Cell<[f32; 4]>,
+    /// Item number saved in the blueprint file, set with [`Breadboard::set_item_number`]
+    item_number: Cell<i32>,
+    /// `PersistentSubObjectIndex` saved in the blueprint file, set with [`Breadboard::set_persistent_sub_object_index`]
+    persistent_sub_object_index: Cell<i32>,
+    /// `PersistentBlockIndex` saved in the blueprint file, set with [`Breadboard::set_persistent_block_index`]
+    persistent_block_index: Cell<i32>,
 }
 
+/// Handle to a breadboard being built
+///
+/// Cloning a breadboard gives another handle to the same breadboard, and every [`Line`] holds one of these handles,
+/// so lines have no lifetime and keep their breadboard alive. Methods take `&self` so lines can be used
+/// with operators, components are only borrowed for the duration of a single method call.
 #[derive(Clone)]
 pub struct Breadboard(Rc<BreadboardInner>);
 
+impl Default for Breadboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Breadboard {
     pub fn new() -> Self {
         Breadboard(Rc::new(BreadboardInner {
             id: NEXT_BREADBOARD_ID.fetch_add(1, Ordering::Relaxed),
             components: RefCell::default(),
+            section_overlays: RefCell::default(),
+            outputs: RefCell::default(),
+            prune_unused: Cell::new(false),
+            canonical_order: Cell::new(false),
+            serialised_info: RefCell::default(),
+            material_cost: Cell::new(BREADBOARD_MATERIAL_COST),
+            local_position: Cell::new([0.0; 3]),
+            local_rotation: Cell::new([0.0, 0.0, 0.0, 1.0]),
+            item_number: Cell::new(0),
+            persistent_sub_object_index: Cell::new(PERSISTENT_INDEX_UNSET),
+            persistent_block_index: Cell::new(PERSISTENT_INDEX_UNSET),
         }))
     }
 
-    fn block_data(&self) -> BlockData {
+    /// Creates a breadboard and wires it up with `f`, so the lines used to build it stay inside the closure
+    ///
+    /// ```
+    /// use bakery::prelude::*;
+    ///
+    /// let blueprint = Breadboard::build(|breadboard| {
+    ///     let altitude = breadboard.altitude(AltitudeOutputType::SeaLevel);
+    ///     let _ = &altitude * 0.5;
+    /// }).to_blueprint_string().unwrap();
+    /// ```
+    pub fn build(f: impl FnOnce(&Breadboard)) -> Breadboard {
+        let breadboard = Breadboard::new();
+        f(&breadboard);
+        breadboard
+    }
+
+    /// Like [`build`](Self::build), but `f` can fail, for wiring with the `try_` methods
+    pub fn try_build<E>(f: impl FnOnce(&Breadboard) -> Result<(), E>) -> Result<Breadboard, E> {
+        let breadboard = Breadboard::new();
+        f(&breadboard)?;
+        Ok(breadboard)
+    }
+
+    /// Calls `f` with each index from 0 to `n`, and returns the lines it returns in index order
+    ///
+    /// This builds `n` parallel copies of a structure which only differ by their index:
+    ///
+    /// ```
+    /// use bakery::prelude::*;
+    ///
+    /// let breadboard = Breadboard::new();
+    /// let altitude = breadboard.altitude(AltitudeOutputType::SeaLevel);
+    /// let thresholds = breadboard.repeat(4, |i, breadboard| {
+    ///     let threshold = breadboard.constant(100.0 * (i as f32 + 1.0));
+    ///     breadboard.gt(altitude.clone(), threshold)
+    /// });
+    /// // number of thresholds the altitude is above
+    /// let _ = thresholds[1..].iter().fold(thresholds[0].clone(), |count, above| &count + above);
+    /// ```
+    pub fn repeat<T: LineValue + ?Sized>(&self, n: usize, mut f: impl FnMut(usize, &Breadboard) -> Line<T>) -> Vec<Line<T>> {
+        (0..n).map(|i| f(i, self)).collect()
+    }
+
+    fn block_data(&self) -> Result<BlockData, BreadboardError> {
         let components = self.0.components.borrow();
+        let section_overlays = self.0.section_overlays.borrow();
+
+        // saved components are renumbered, but the wire map and overlays still use the original component indices
+        let mut saved_components: Vec<_> = if self.0.prune_unused.get() {
+            let needed = needed_components(&components, &self.0.outputs.borrow());
+            components.iter()
+                .enumerate()
+                .filter(|(i, _)| needed[*i])
+                .collect()
+        } else {
+            components.iter().enumerate().collect()
+        };
+
+        let mut wire_seeds = vec![None; components.len()];
+        if self.0.canonical_order.get() {
+            // the sort is stable, so components with the same structure stay in insertion order
+            let hashes = structural_hashes(&components, &section_overlays);
+            saved_components.sort_by_key(|(i, _)| hashes[*i]);
+
+            // components with the same structure are told apart by how many came before them
+            let mut duplicates: HashMap<u64, u64> = HashMap::new();
+            for (i, _) in saved_components.iter() {
+                let count = duplicates.entry(hashes[*i]).or_insert(0);
+                wire_seeds[*i] = Some(fnv1a(hashes[*i], &count.to_le_bytes()));
+                *count += 1;
+            }
+        }
 
+        if saved_components.len() > MAX_COMPONENTS {
+            return Err(BreadboardError::TooManyComponents {
+                count: saved_components.len(),
+            });
+        }
+
+        // components which are left out are never inputs of saved components, so they don't need outputs
         let mut wire_map = BreadboardWireMap::new(components.len());
+        for (i, component) in saved_components.iter() {
+            component.check_saveable(*i)?;
+            wire_map.set_num_outputs(*i, component.num_outputs(), wire_seeds[*i]);
+        }
+
         let mut data = BlockData::default();
 
-        // don't know what these sections do, but ftd seems to generate these empty sections for a breadboard
-        data.add_section_data(3000.into(), SectionData::default());
-        data.add_section_data(72541.into(), SectionData::default());
+        for section_id in EMPTY_SECTION_IDS {
+            data.add_section_data(section_id.into(), SectionData::default());
+        }
 
         let mut breadboard_main_section = SectionData::default();
-        for (i, component) in components.iter().enumerate() {
+        for (saved_index, (i, component)) in saved_components.into_iter().enumerate() {
             breadboard_main_section.add_entry(
-                (2 * i).try_into().unwrap(),
+                (2 * saved_index).try_into().unwrap(),
                 DataEntry::Uuid(component.uuid()),
             );
 
-            let component_id = COMPONENT_ID_START + u32::try_from(i).unwrap();
+            let component_id = COMPONENT_ID_START + u32::try_from(saved_index).unwrap();
 
             breadboard_main_section.add_entry(
-                (2 * i + 1).try_into().unwrap(),
+                (2 * saved_index + 1).try_into().unwrap(),
                 DataEntry::U32(component_id),
             );
 
             let mut component_section_data = wire_map.get_component_data_section_with_inputs_and_outputs(i, &**component);
 
-            let position = ComponentPosition::new(200.0 * i as f32, 0.0);
+            let position = ComponentPosition::new(200.0 * saved_index as f32, 0.0);
             position.set_section_data_position(&mut component_section_data);
 
+            if let Some(overlay) = section_overlays.get(&i) {
+                component_section_data.merge(overlay);
+            }
+
             data.add_section_data(SectionId::new(component_id), component_section_data);
         }
 
-        // breadboard looks at this section to instantiate components
-        data.add_section_data(9999.into(), breadboard_main_section);
+        data.add_section_data(MAIN_SECTION_ID.into(), breadboard_main_section);
 
-        data
+        Ok(data)
     }
 
-    pub fn save_to_prefab_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+    /// Builds the blueprint data for a blueprint containing only this breadboard, with the breadboard as block 0
+    pub fn blueprint_data(&self) -> Result<BlueprintData, BreadboardError> {
         let mut data = BlueprintData::default();
-        data.add_block_data(0.into(), self.block_data());
-        let data = data.serialize_to_bp_data_string();
+        data.add_block_data(0.into(), self.block_data()?);
+
+        Ok(data)
+    }
+
+    /// Writes a prefab blueprint file containing only this breadboard to `writer`
+    pub fn write_blueprint<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let data = self.blueprint_data()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+        self.write_blueprint_file(&data, writer)
+    }
 
-        let mut bp_file = String::from(r#"{"FileModelVersion":{"Major":1,"Minor":0},"Name":"TEST_BREADBOARD","Version":0,"SavedTotalBlockCount":1,"SavedMaterialCost":10.0,"ContainedMaterialCost":0.0,"ItemDictionary":{"227":"5ef97d26-1196-4b1a-ba1d-fd539c26b684","0":"75a78e48-0848-45ee-9df2-e2b328c1933d"},"Blueprint":{"ContainedMaterialCost":0.0,"CSI":[-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0],"COL":null,"SCs":[],"BLP":["0,0,0"],"BLR":[0],"BP1":null,"BP2":null,"BCI":[0],"BEI":null,"BlockData":""#);
-        bp_file.push_str(&data);
-        bp_file.push_str(r#"","VehicleData":"sct0AAAAAAAA","designChanged":false,"blueprintVersion":0,"blueprintName":"TEST_BREADBOARD","SerialisedInfo":{"JsonDictionary":{},"IsEmpty":true},"Name":null,"ItemNumber":0,"LocalPosition":"0,0,0","LocalRotation":"0,0,0,0","ForceId":0,"TotalBlockCount":1,"MaxCords":"1,1,1","MinCords":"0,0,0","BlockIds":[227],"BlockState":null,"AliveCount":1,"BlockStringData":null,"BlockStringDataIds":null,"GameVersion":"3.8.0.4","PersistentSubObjectIndex":-1,"PersistentBlockIndex":-1,"AuthorDetails":{"Valid":true,"ForeignBlocks":0,"CreatorId":"0ab41fc3-fd53-4843-becf-7608b7c315b7","ObjectId":"5bb43b25-8e79-4e92-9db3-076b363114a7","CreatorReadableName":"DeltaForce","HashV1":"6831413c85b3e408740dc00f5580382c"},"BlockCount":1}}"#);
-        
-        std::fs::write(path, bp_file)
+    /// Contents of a prefab blueprint file containing only this breadboard, see [`write_blueprint`](Self::write_blueprint)
+    pub fn to_blueprint_string(&self) -> Result<String, BreadboardError> {
+        let data = self.blueprint_data()?;
+
+        let mut blueprint = Vec::new();
+        // the block has at most MAX_SECTIONS sections because of the component limit, so this can't fail
+        self.write_blueprint_file(&data, &mut blueprint).expect("writing to a vec can't fail");
+
+        // the json parts are utf8 and the block data is base64
+        Ok(String::from_utf8(blueprint).unwrap())
+    }
+
+    /// Saves a prefab blueprint file containing only this breadboard to `path`
+    ///
+    /// The blueprint data is built before the file is created, so if the breadboard can't be saved
+    /// an existing file at `path` is left unchanged.
+    pub fn save_to_prefab_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let data = self.blueprint_data()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_blueprint_file(&data, &mut writer)?;
+        writer.flush()
     }
 
     pub fn save_to_prefab_file_in_game_folder(&self, name: &str) -> io::Result<()> {
@@ -138,14 +450,333 @@ impl Breadboard {
         self.save_to_prefab_file(path)
     }
 
+    /// Estimates how expensive this breadboard is for ftd to update every frame
+    ///
+    /// This is only a heuristic for comparing breadboards, it is not ftd's actual performance cost.
+    pub fn estimated_cost(&self) -> f32 {
+        self.0.components.borrow()
+            .iter()
+            .map(|component| {
+                let ftd_uuid = component.ftd_uuid();
+
+                COMPONENT_COSTS.iter()
+                    .find(|(uuid, _)| *uuid == ftd_uuid)
+                    .map_or(DEFAULT_COMPONENT_COST, |(_, cost)| *cost)
+            })
+            .sum()
+    }
+
+    /// Adds extra entries to the section data of the component which outputs `line`
+    ///
+    /// This allows setting component options which this crate does not support yet.
+    /// The entries replace any entries with the same id the component would normally write
+    /// (including the wiring and position entries), and are not checked against what ftd expects.
+    pub fn set_component_entries<T: LineValue + ?Sized>(&self, line: &Line<T>, entries: &SectionData) {
+        self.verify_line(line);
+
+        self.0.section_overlays.borrow_mut()
+            .entry(line.inner.component_index)
+            .or_default()
+            .merge(entries);
+    }
+
+    /// Sets a single extra entry on the component which outputs `line`, see [`set_component_entries`](Self::set_component_entries)
+    pub fn set_component_entry<T: LineValue + ?Sized>(&self, line: &Line<T>, id: DataEntryId, entry: DataEntry) {
+        self.set_component_entries(line, &SectionData::default().with_entry(id, entry));
+    }
+
+    /// Sets a raw bytes entry on the component which outputs `line`, for options this crate does not model
+    ///
+    /// The bytes are saved as given, without being validated, like other entries set with
+    /// [`set_component_entries`](Self::set_component_entries). Ftd stores each entry with a 1 byte length,
+    /// so bytes longer than 255 are saved as consecutive entries with the same id, each holding up to 255 bytes.
+    pub fn set_component_bytes<T: LineValue + ?Sized>(&self, line: &Line<T>, id: DataEntryId, bytes: impl Into<Vec<u8>>) {
+        self.set_component_entry(line, id, DataEntry::Bytes(bytes.into()));
+    }
+
+    /// Sets the material cost saved in the blueprint file, which defaults to [`BREADBOARD_MATERIAL_COST`]
+    ///
+    /// Ftd recomputes the cost when the blueprint is loaded, this only changes what tools reading the file see.
+    /// A negative or non finite cost is saved as 0, since json can't represent infinity or nan.
+    pub fn set_material_cost(&self, cost: f32) {
+        self.0.material_cost.set(if cost.is_finite() { cost.max(0.0) } else { 0.0 });
+    }
+
+    /// Writes the blueprint file json around the base64 string of `data`
+    fn write_blueprint_file<W: Write>(&self, data: &BlueprintData, writer: &mut W) -> io::Result<()> {
+        writer.write_all(BLUEPRINT_FILE_START.as_bytes())?;
+        write!(writer, "{:?}", self.0.material_cost.get())?;
+        writer.write_all(BLUEPRINT_FILE_BEFORE_CSI.as_bytes())?;
+        write_csi(writer)?;
+        writer.write_all(BLUEPRINT_FILE_MIDDLE.as_bytes())?;
+        data.write_bp_data_string(writer)?;
+        writer.write_all(BLUEPRINT_FILE_AFTER_DATA.as_bytes())?;
+        write_serialised_info(&self.0.serialised_info.borrow(), writer)?;
+        writer.write_all(BLUEPRINT_FILE_BEFORE_ITEM_NUMBER.as_bytes())?;
+        write!(writer, "{}", self.0.item_number.get())?;
+        writer.write_all(br#","LocalPosition":""#)?;
+        write_placement_values(&self.0.local_position.get(), writer)?;
+        writer.write_all(br#"","LocalRotation":""#)?;
+        write_placement_values(&self.0.local_rotation.get(), writer)?;
+        writer.write_all(BLUEPRINT_FILE_BEFORE_PERSISTENT_INDICES.as_bytes())?;
+        write!(writer, "{}", self.0.persistent_sub_object_index.get())?;
+        writer.write_all(br#","PersistentBlockIndex":"#)?;
+        write!(writer, "{}", self.0.persistent_block_index.get())?;
+        writer.write_all(BLUEPRINT_FILE_END.as_bytes())
+    }
+
+    /// Sets the position saved in the blueprint file, which is where the prefab is placed relative to what it is attached to
+    ///
+    /// Ftd saves this as the string `"x,y,z"`. It defaults to `0,0,0`, and non finite values are saved as 0.
+    pub fn set_local_position(&self, x: f32, y: f32, z: f32) {
+        self.0.local_position.set([x, y, z]);
+    }
+
+    /// Sets the rotation saved in the blueprint file, as a quaternion with the components `x, y, z, w`
+    ///
+    /// Ftd saves this as the string `"x,y,z,w"`, in unity's component order. It defaults to the identity rotation `0,0,0,1`.
+    /// The quaternion is saved as given, so it should be normalized. Non finite values are saved as 0.
+    pub fn set_local_rotation(&self, x: f32, y: f32, z: f32, w: f32) {
+        self.0.local_rotation.set([x, y, z, w]);
+    }
+
+    /// Sets the `ItemNumber` saved in the blueprint file, which defaults to 0
+    ///
+    /// This is the key in the blueprint's `ItemDictionary` of the item the construct is built on. Sub constructs
+    /// such as turrets use it for the block they are mounted on, the root construct of a prefab saves 0.
+    /// When splicing the breadboard into another vehicle as a sub construct, set it to match that vehicle's dictionary.
+    pub fn set_item_number(&self, item_number: i32) {
+        self.0.item_number.set(item_number);
+    }
+
+    /// `ItemNumber` saved in the blueprint file, see [`set_item_number`](Self::set_item_number)
+    pub fn item_number(&self) -> i32 {
+        self.0.item_number.get()
+    }
+
+    /// Sets the `PersistentSubObjectIndex` saved in the blueprint file, which defaults to [`PERSISTENT_INDEX_UNSET`]
+    ///
+    /// Ftd gives every sub construct of a vehicle an index which stays the same across saves, and uses it to
+    /// match up the sub construct with data stored elsewhere in the vehicle. A standalone prefab has no index.
+    pub fn set_persistent_sub_object_index(&self, index: i32) {
+        self.0.persistent_sub_object_index.set(index);
+    }
+
+    /// `PersistentSubObjectIndex` saved in the blueprint file, see [`set_persistent_sub_object_index`](Self::set_persistent_sub_object_index)
+    pub fn persistent_sub_object_index(&self) -> i32 {
+        self.0.persistent_sub_object_index.get()
+    }
+
+    /// Sets the `PersistentBlockIndex` saved in the blueprint file, which defaults to [`PERSISTENT_INDEX_UNSET`]
+    ///
+    /// This is the persistent index of the block in the parent construct which a sub construct is attached to.
+    /// A standalone prefab is not attached to anything, so it has no index.
+    pub fn set_persistent_block_index(&self, index: i32) {
+        self.0.persistent_block_index.set(index);
+    }
+
+    /// `PersistentBlockIndex` saved in the blueprint file, see [`set_persistent_block_index`](Self::set_persistent_block_index)
+    pub fn persistent_block_index(&self) -> i32 {
+        self.0.persistent_block_index.get()
+    }
+
+    /// Sets `key` to `value` in the `SerialisedInfo` dictionary of the saved blueprint, replacing any previous value
+    ///
+    /// Ftd stores data for some blueprint wide features in this dictionary, which this crate does not generate itself.
+    /// This allows emitting those entries anyway, the values are saved as json strings and are not checked against what ftd expects.
+    /// `IsEmpty` is only false once an entry is set.
+    pub fn set_serialised_info(&self, key: impl Into<String>, value: impl Into<String>) {
+        self.0.serialised_info.borrow_mut().insert(key.into(), value.into());
+    }
+
+    /// Returns the number of components in this breadboard, including any which are pruned when saving
+    pub fn component_count(&self) -> usize {
+        self.0.components.borrow().len()
+    }
+
+    /// Returns true if this breadboard has no components
+    pub fn is_empty(&self) -> bool {
+        self.component_count() == 0
+    }
+
+    /// Returns the number of outputs of the component at `component_index`, or `None` if there is no such component
+    pub fn component_num_outputs(&self, component_index: usize) -> Option<usize> {
+        self.0.components.borrow()
+            .get(component_index)
+            .map(|component| component.num_outputs())
+    }
+
+    /// Returns how many times each output of each component is used as an input, indexed by component index then output index
+    ///
+    /// An output wired into the same component twice counts twice. Lines marked with [`mark_output`](Self::mark_output)
+    /// don't count, since they aren't inputs. A high count means one value is read in many places, which may be worth
+    /// a look when a breadboard is hard to follow.
+    pub fn output_use_counts(&self) -> Vec<Vec<usize>> {
+        let components = self.0.components.borrow();
+        let mut counts: Vec<Vec<usize>> = components.iter()
+            .map(|component| vec![0; component.num_outputs()])
+            .collect();
+
+        for component in components.iter() {
+            for input in component.inputs() {
+                if let Some(count) = counts.get_mut(input.component_index).and_then(|outputs| outputs.get_mut(input.output_index)) {
+                    *count += 1;
+                }
+            }
+        }
+
+        counts
+    }
+
+    /// Marks `line` as a final output of the breadboard, so the components it is computed from count as used
+    ///
+    /// See [`unused_components`](Self::unused_components) and [`prune_unused`](Self::prune_unused).
+    pub fn mark_output<T: LineValue + ?Sized>(&self, line: &Line<T>) {
+        self.verify_line(line);
+        self.0.outputs.borrow_mut().push(line.inner);
+    }
+
+    /// Returns the indices of all components which are not needed to compute any line marked with [`mark_output`](Self::mark_output)
+    pub fn unused_components(&self) -> Vec<usize> {
+        let needed = needed_components(&self.0.components.borrow(), &self.0.outputs.borrow());
+
+        needed.into_iter()
+            .enumerate()
+            .filter(|(_, needed)| !needed)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Leaves out the [`unused_components`](Self::unused_components) when this breadboard is saved
+    ///
+    /// Only lines marked with [`mark_output`](Self::mark_output) are kept, so if no lines are marked the saved breadboard is empty.
+    /// Components are only left out of the saved data, so existing lines stay valid and more components can still be added.
+    pub fn prune_unused(&self) {
+        self.0.prune_unused.set(true);
+    }
+
+    /// Saves components sorted by their structure instead of in the order they were added
+    ///
+    /// Components are saved with ids and positions in the order they are sorted, so normally adding a component early
+    /// in a generator changes every component after it. With this set, each component is sorted by a hash of its kind,
+    /// its options, its extra entries and the components it reads from, so the order of other components
+    /// does not depend on when a component was added, which keeps diffs of generated blueprints small.
+    /// The uuids of the wires between components are also derived from this hash instead of being random.
+    /// The order only depends on this crate's version, not on the platform or on previous runs.
+    pub fn canonical_order(&self) {
+        self.0.canonical_order.set(true);
+    }
+
+    /// Checks everything which would stop this breadboard from being saved, and returns every problem found
+    ///
+    /// This finds the same problems saving does, but reports all of them instead of only the first one.
+    /// If some lines are marked with [`mark_output`](Self::mark_output) and unused components are not pruned,
+    /// each unused component is also reported as a warning, see [`ValidationError::is_warning`].
+    /// Multiply components with more than [`MAX_MULTIPLY_INPUTS`] inputs are reported as a warning as well.
+    /// An error is returned if there are any problems, including when they are all warnings.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let components = self.0.components.borrow();
+        let outputs = self.0.outputs.borrow();
+        let mut errors = Vec::new();
+
+        let needed = needed_components(&components, &outputs);
+        let prune_unused = self.0.prune_unused.get();
+        let saved = |i: usize| !prune_unused || needed[i];
+
+        let saved_count = (0..components.len()).filter(|i| saved(*i)).count();
+        if saved_count > MAX_COMPONENTS {
+            errors.push(ValidationError::Breadboard(BreadboardError::TooManyComponents {
+                count: saved_count,
+            }));
+        }
+
+        for (i, component) in components.iter().enumerate() {
+            if !saved(i) {
+                continue;
+            }
+
+            for (input_index, line) in component.inputs().iter().enumerate() {
+                let exists = components.get(line.component_index)
+                    .is_some_and(|input_component| line.output_index < input_component.num_outputs());
+
+                if !exists {
+                    errors.push(ValidationError::InvalidInput {
+                        component_index: i,
+                        input_index,
+                        line: *line,
+                    });
+                }
+            }
+
+            if let Err(error) = component.check_saveable(i) {
+                errors.push(ValidationError::Component {
+                    component_index: i,
+                    error,
+                });
+            }
+
+            if component.ftd_uuid() == MULTIPLY_UUID && component.inputs().len() > MAX_MULTIPLY_INPUTS {
+                errors.push(ValidationError::ManyMultiplyInputs {
+                    component_index: i,
+                    count: component.inputs().len(),
+                });
+            }
+
+            if !outputs.is_empty() && !needed[i] {
+                errors.push(ValidationError::UnusedComponent {
+                    component_index: i,
+                });
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Returns true if the line is the output of a component in this breadboard
+    pub fn owns_line<T: LineValue + ?Sized>(&self, line: &Line<T>) -> bool {
+        self.0.id == line.breadboard.0.id
+    }
+
+    fn try_verify_line<T: LineValue + ?Sized>(&self, line: &Line<T>) -> Result<(), BreadboardError> {
+        if self.owns_line(line) {
+            Ok(())
+        } else {
+            Err(BreadboardError::ForeignLine)
+        }
+    }
+
+    /// Checks that a component can be inserted, which the `try_` methods do before inserting anything
+    fn try_verify_insertable(&self) -> Result<(), BreadboardError> {
+        self.0.components.try_borrow_mut()
+            .map(|_| ())
+            .map_err(|_| BreadboardError::ComponentsBorrowed)
+    }
+
     fn verify_line<T: LineValue + ?Sized>(&self, line: &Line<T>) {
-        assert!(self.0.id == line.breadboard.0.id, "invalid line passed into breadboard");
+        assert!(self.owns_line(line), "invalid line passed into breadboard");
+    }
+
+    fn try_verify_group<T: LineValue, G: InputGroup<T> + ?Sized>(&self, group: &G) -> Result<(), BreadboardError> {
+        group.iter_lines().try_for_each(|line| self.try_verify_line(line))
+    }
+
+    fn verify_group<T: LineValue, G: InputGroup<T> + ?Sized>(&self, group: &G) {
+        group.iter_lines().for_each(|line| self.verify_line(line));
     }
 
     /// Inserts the component into the breadboard and returns its index
     fn insert_component<C: Component + 'static>(&self, component: C) -> usize {
-        let mut components = self.0.components.borrow_mut();
-        components.push(Box::new(component));
+        self.insert_boxed_component(Box::new(component))
+    }
+
+    fn insert_boxed_component(&self, component: Box<dyn Component>) -> usize {
+        let mut components = self.0.components.try_borrow_mut()
+            .unwrap_or_else(|_| panic!("{}", BreadboardError::ComponentsBorrowed));
+        components.push(component);
         components.len() - 1
     }
 
@@ -154,15 +785,38 @@ impl Breadboard {
         Line::new(self.clone(), self.insert_component(component), 0)
     }
 
+    /// Constant 1, the value comparisons and logic output for true
+    pub fn b_true(&self) -> Line<BNumber> {
+        self.constant(1.0)
+    }
+
+    /// Constant 0, the value comparisons and logic output for false
+    pub fn b_false(&self) -> Line<BNumber> {
+        self.constant(0.0)
+    }
+
+    /// Constant number, clamped to [`COMPONENT_VALUE_LIMIT`]
     pub fn constant(&self, n: f32) -> Line<BNumber> {
         self.insert_component_with_output(Constant {
-            n: n.clamp(-10000.0, 10000.0),
+            n: clamp_setting(n, COMPONENT_VALUE_LIMIT),
         })
     }
 
+    /// Inserts a constant for each value, and returns their lines in the same order
+    ///
+    /// Each value is clamped like in [`constant`](Self::constant).
+    pub fn constants(&self, values: &[f32]) -> Vec<Line<BNumber>> {
+        values.iter()
+            .map(|value| self.constant(*value))
+            .collect()
+    }
+
+    /// Random number between `min` and `max`, which are clamped to [`COMPONENT_VALUE_LIMIT`]
+    ///
+    /// If `max` is below `min` it is raised to `min`.
     pub fn random_number(&self, min: f32, max: f32) -> Line<BNumber> {
-        let min = min.clamp(-10000.0, 10000.0);
-        let max = max.clamp(min, 10000.0);
+        let min = clamp_setting(min, COMPONENT_VALUE_LIMIT);
+        let max = clamp_setting(max, COMPONENT_VALUE_LIMIT).max(min);
 
         self.insert_component_with_output(RandomInput {
             min,
@@ -193,7 +847,91 @@ impl Breadboard {
     }
 }
 
+/// Handle to the copies of another breadboard's components made by [`Breadboard::merge`]
+pub struct MergedBreadboard {
+    breadboard: Breadboard,
+    source_id: usize,
+    /// Index in the parent breadboard of the first copied component
+    component_offset: usize,
+    component_count: usize,
+}
+
+impl MergedBreadboard {
+    /// Returns the line in the parent breadboard which is the copy of `line` from the merged breadboard
+    ///
+    /// # Panics
+    ///
+    /// Panics if `line` is not from the merged breadboard, or its component was added after the merge.
+    pub fn line<T: LineValue + ?Sized>(&self, line: &Line<T>) -> Line<T> {
+        assert!(line.breadboard.0.id == self.source_id, "line is not from the merged breadboard");
+        assert!(line.inner.component_index < self.component_count, "line was added to the merged breadboard after it was merged");
+
+        Line::new(self.breadboard.clone(), self.component_offset + line.inner.component_index, line.inner.output_index)
+    }
+}
+
+impl Breadboard {
+    /// Copies every component of `other` into this breadboard, keeping their wiring
+    ///
+    /// The copies are added after the existing components, together with their extra entries and marked outputs.
+    /// Use the returned [`MergedBreadboard`] to get the copies of lines from `other`, to wire them to this breadboard.
+    /// `other` is not changed, and components added to it later are not copied.
+    /// Ftd component ids are only assigned when saving, so the copies never collide with existing components.
+    pub fn merge(&self, other: &Breadboard) -> MergedBreadboard {
+        // everything is copied before borrowing this breadboard mutably, so a breadboard can be merged into itself
+        let components: Vec<_> = other.0.components.borrow()
+            .iter()
+            .map(|component| component.clone_box())
+            .collect();
+        let section_overlays = other.0.section_overlays.borrow().clone();
+        let outputs = other.0.outputs.borrow().clone();
+
+        let component_offset = self.0.components.borrow().len();
+        let offset_line = |line: LineInner| LineInner {
+            component_index: component_offset + line.component_index,
+            output_index: line.output_index,
+        };
+
+        let component_count = components.len();
+        for mut component in components {
+            for input in component.inputs_mut() {
+                *input = offset_line(*input);
+            }
+
+            self.insert_boxed_component(component);
+        }
+
+        self.0.section_overlays.borrow_mut()
+            .extend(section_overlays.into_iter().map(|(i, overlay)| (component_offset + i, overlay)));
+        self.0.outputs.borrow_mut()
+            .extend(outputs.into_iter().map(offset_line));
+
+        MergedBreadboard {
+            breadboard: self.clone(),
+            source_id: other.0.id,
+            component_offset,
+            component_count,
+        }
+    }
+}
+
+/// Type of each output of the target info component, in output order
+///
+/// See [`TargetInfoOutputs`] for what each output is.
+const TARGET_INFO_OUTPUT_TYPES: [ValueType; 7] = [
+    ValueType::Number,
+    ValueType::Number,
+    ValueType::Number,
+    ValueType::Number,
+    ValueType::Vector,
+    ValueType::Vector,
+    ValueType::Number,
+];
+
 /// This contains all info returnd by the primary target info component
+///
+/// The fields are in the order of the component's outputs, so `present` is output 0 and `volume` is output 6.
+/// Outputs can also be looked up by index with [`number_output`](Self::number_output) and [`vector_output`](Self::vector_output).
 #[derive(Clone)]
 pub struct TargetInfoOutputs {
     pub present: Line<BNumber>,
@@ -206,6 +944,23 @@ pub struct TargetInfoOutputs {
     pub volume: Line<BNumber>,
 }
 
+impl TargetInfoOutputs {
+    /// Number output at `index`, or `None` if the component has no number output at `index`
+    pub fn number_output(&self, index: usize) -> Option<Line<BNumber>> {
+        self.output(index, ValueType::Number)
+    }
+
+    /// Vector output at `index`, or `None` if the component has no vector output at `index`
+    pub fn vector_output(&self, index: usize) -> Option<Line<BVector3>> {
+        self.output(index, ValueType::Vector)
+    }
+
+    fn output<T: LineValue>(&self, index: usize, value_type: ValueType) -> Option<Line<T>> {
+        (*TARGET_INFO_OUTPUT_TYPES.get(index)? == value_type)
+            .then(|| Line::new(self.present.breadboard.clone(), self.present.inner.component_index, index))
+    }
+}
+
 impl Breadboard {
     pub fn target_info(&self) -> TargetInfoOutputs {
         let component_id = self.insert_component(TargetInfo);
@@ -221,16 +976,44 @@ impl Breadboard {
         }
     }
 
+    /// Inserts a multiply component with `multiplier` and every line of `inputs` wired to it
+    ///
+    /// Every line is wired to its own input of the component, so a line passed more than once is wired once for
+    /// each time it is passed. Use [`multiply_distinct`](Self::multiply_distinct) to wire each line only once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any input is from a different breadboard.
     // TODO: maybe allow vectors, I think multiply tachnically allows it in some cases
     pub fn multiply<T: InputGroup<BNumber>>(&self, inputs: &T, multiplier: f32) -> Line<BNumber> {
-        let multiplier = multiplier.clamp(-100.0, 100.0);
+        self.verify_group(inputs);
+        self.insert_multiply(inputs.as_vec(), multiplier)
+    }
+
+    /// Like [`multiply`](Self::multiply), but lines passed more than once are only wired once
+    pub fn multiply_distinct<T: InputGroup<BNumber>>(&self, inputs: &T, multiplier: f32) -> Line<BNumber> {
+        self.verify_group(inputs);
+        self.insert_multiply(distinct_inputs(inputs), multiplier)
+    }
+
+    fn insert_multiply(&self, inputs: Vec<LineInner>, multiplier: f32) -> Line<BNumber> {
+        let multiplier = clamp_setting(multiplier, MULTIPLIER_LIMIT);
 
         self.insert_component_with_output(Multiply {
             multiplier,
-            inputs: inputs.as_vec(),
+            inputs,
         })
     }
 
+    /// Outputs `passthrough` while `switch_signal` is above the threshold, and the open value otherwise
+    ///
+    /// The switch is closed when `switch_signal > threshold` so passthrough is output, and open when
+    /// `switch_signal <= threshold` so the open value is output instead. See [`SwitchOptions`] for the defaults.
+    /// The threshold and open value are clamped to [`COMPONENT_VALUE_LIMIT`].
+    ///
+    /// Comparisons such as [`gt`](Self::gt) and logic such as [`and`](Self::and) output 1 for true and 0 for false,
+    /// so they can be used as the switch signal directly. The default threshold of 0.5 is between the two,
+    /// which closes the switch exactly when the condition is true. A threshold of 1 or more never closes it for a condition.
     // TODO: maybe allow vectore here as well, switch also works with vectors, but the behavior is very wierd (vector magnitude is passed through)
     pub fn switch(&self, passthrough: Line<BNumber>, switch_signal: Line<BNumber>, options: SwitchOptions) -> Line<BNumber> {
         self.verify_line(&passthrough);
@@ -238,10 +1021,41 @@ impl Breadboard {
 
         self.insert_component_with_output(Switch {
             inputs: [passthrough.inner, switch_signal.inner],
-            threshhold: options.threshhold.clamp(-10000.0, 10000.0),
-            open_value: options.open_value.clamp(-10000.0, 10000.0),
+            threshold: clamp_setting(options.threshold, COMPONENT_VALUE_LIMIT),
+            open_value: clamp_setting(options.open_value, COMPONENT_VALUE_LIMIT),
         })
     }
+
+    /// Like [`multiply`](Self::multiply), but returns an error instead of panicking if any input is from a different breadboard
+    pub fn try_multiply<T: InputGroup<BNumber>>(&self, inputs: &T, multiplier: f32) -> Result<Line<BNumber>, BreadboardError> {
+        self.try_verify_group(inputs)?;
+        self.try_verify_insertable()?;
+
+        Ok(self.multiply(inputs, multiplier))
+    }
+
+    /// Like [`multiply_distinct`](Self::multiply_distinct), but returns an error instead of panicking if any input
+    /// is from a different breadboard
+    pub fn try_multiply_distinct<T: InputGroup<BNumber>>(&self, inputs: &T, multiplier: f32) -> Result<Line<BNumber>, BreadboardError> {
+        self.try_verify_group(inputs)?;
+        self.try_verify_insertable()?;
+
+        Ok(self.multiply_distinct(inputs, multiplier))
+    }
+
+    /// Like [`switch`](Self::switch), but returns an error instead of panicking if any input is from a different breadboard
+    pub fn try_switch(
+        &self,
+        passthrough: Line<BNumber>,
+        switch_signal: Line<BNumber>,
+        options: SwitchOptions,
+    ) -> Result<Line<BNumber>, BreadboardError> {
+        self.try_verify_line(&passthrough)?;
+        self.try_verify_line(&switch_signal)?;
+        self.try_verify_insertable()?;
+
+        Ok(self.switch(passthrough, switch_signal, options))
+    }
 }
 
 struct ComponentPosition {
@@ -271,7 +1085,7 @@ impl ComponentPosition {
     }
 }
 
-trait Component {
+trait Component: CloneComponent {
     fn ftd_uuid(&self) -> Uuid;
 
     fn uuid(&self) -> Uuid {
@@ -281,17 +1095,42 @@ trait Component {
     fn section_data(&self) -> SectionData;
     fn num_outputs(&self) -> usize;
     fn inputs(&self) -> &[LineInner];
+    fn inputs_mut(&mut self) -> &mut [LineInner];
+
+    /// Computes the outputs for one frame of a [`Simulation`], or returns `None` for sensors
+    ///
+    /// `previous_outputs` are the outputs from the previous frame, and are empty on the first frame.
+    /// If an input has a type the component does not accept, an error message is returned.
+    fn simulate(&self, _inputs: &[SimValue], _previous_outputs: &[SimValue]) -> Option<Result<Vec<SimValue>, String>> {
+        None
+    }
+
+    /// Checks the component can be saved, `component_index` is only used for the error
+    fn check_saveable(&self, _component_index: usize) -> Result<(), BreadboardError> {
+        Ok(())
+    }
+}
+
+/// Allows cloning boxed components, this is implemented for every component which is `Clone`
+trait CloneComponent {
+    fn clone_box(&self) -> Box<dyn Component>;
+}
+
+impl<C: Component + Clone + 'static> CloneComponent for C {
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
 }
 
 // TODO: support constant strings
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Constant {
     n: f32,
 }
 
 impl Component for Constant {
     fn ftd_uuid(&self) -> Uuid {
-        uuid!("9142c70d-7833-41cd-804d-554e990b6904")
+        CONSTANT_UUID
     }
 
     fn section_data(&self) -> SectionData {
@@ -306,9 +1145,17 @@ impl Component for Constant {
     fn inputs(&self) -> &[LineInner] {
         &[]
     }
+
+    fn inputs_mut(&mut self) -> &mut [LineInner] {
+        &mut []
+    }
+
+    fn simulate(&self, _inputs: &[SimValue], _previous_outputs: &[SimValue]) -> Option<Result<Vec<SimValue>, String>> {
+        Some(Ok(vec![SimValue::Number(self.n.into())]))
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct RandomInput {
     min: f32,
     max: f32,
@@ -316,7 +1163,7 @@ struct RandomInput {
 
 impl Component for RandomInput {
     fn ftd_uuid(&self) -> Uuid {
-        uuid!("268b7db2-bccf-41fd-8cfa-3f21d2f2bacb")
+        RANDOM_INPUT_UUID
     }
 
     fn section_data(&self) -> SectionData {
@@ -331,6 +1178,10 @@ impl Component for RandomInput {
     fn inputs(&self) -> &[LineInner] {
         &[]
     }
+
+    fn inputs_mut(&mut self) -> &mut [LineInner] {
+        &mut []
+    }
 }
 
 #[repr(u32)]
@@ -343,14 +1194,14 @@ pub enum AltitudeOutputType {
     TerrainAndSea,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Altitude {
     typ: AltitudeOutputType,
 }
 
 impl Component for Altitude {
     fn ftd_uuid(&self) -> Uuid {
-        uuid!("ae46572b-dff8-4153-97dc-146108f3a64f")
+        ALTITUDE_UUID
     }
 
     fn section_data(&self) -> SectionData {
@@ -365,14 +1216,18 @@ impl Component for Altitude {
     fn inputs(&self) -> &[LineInner] {
         &[]
     }
+
+    fn inputs_mut(&mut self) -> &mut [LineInner] {
+        &mut []
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Position;
 
 impl Component for Position {
     fn ftd_uuid(&self) -> Uuid {
-        uuid!("e20d6a3a-c0b9-4665-8749-7a85c40afabe")
+        POSITION_UUID
     }
 
     fn section_data(&self) -> SectionData {
@@ -386,6 +1241,10 @@ impl Component for Position {
     fn inputs(&self) -> &[LineInner] {
         &[]
     }
+
+    fn inputs_mut(&mut self) -> &mut [LineInner] {
+        &mut []
+    }
 }
 
 #[repr(u32)]
@@ -395,14 +1254,14 @@ pub enum SpeedOutputType {
     ForwardsMagnitude = 3,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Speed {
     typ: SpeedOutputType,
 }
 
 impl Component for Speed {
     fn ftd_uuid(&self) -> Uuid {
-        uuid!("c8f64443-b81f-4b75-8105-18cd6e453539")
+        SPEED_UUID
     }
 
     fn section_data(&self) -> SectionData {
@@ -417,6 +1276,10 @@ impl Component for Speed {
     fn inputs(&self) -> &[LineInner] {
         &[]
     }
+
+    fn inputs_mut(&mut self) -> &mut [LineInner] {
+        &mut []
+    }
 }
 
 #[repr(u32)]
@@ -428,14 +1291,14 @@ pub enum VelocityOutputType {
 
 // velocity is seperated from speed even though they are the same underlying component
 // because these are the speed modes outputing a vector
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Velocity {
     typ: VelocityOutputType,
 }
 
 impl Component for Velocity {
     fn ftd_uuid(&self) -> Uuid {
-        uuid!("c8f64443-b81f-4b75-8105-18cd6e453539")
+        SPEED_UUID
     }
 
     fn section_data(&self) -> SectionData {
@@ -450,14 +1313,18 @@ impl Component for Velocity {
     fn inputs(&self) -> &[LineInner] {
         &[]
     }
+
+    fn inputs_mut(&mut self) -> &mut [LineInner] {
+        &mut []
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct TargetInfo;
 
 impl Component for TargetInfo {
     fn ftd_uuid(&self) -> Uuid {
-        uuid!("5390bcf0-d09d-40b8-99a3-8d3752e656c6")
+        TARGET_INFO_UUID
     }
 
     fn section_data(&self) -> SectionData {
@@ -465,15 +1332,193 @@ impl Component for TargetInfo {
     }
 
     fn num_outputs(&self) -> usize {
-        7
+        TARGET_INFO_OUTPUT_TYPES.len()
     }
 
     fn inputs(&self) -> &[LineInner] {
         &[]
     }
+
+    fn inputs_mut(&mut self) -> &mut [LineInner] {
+        &mut []
+    }
+}
+
+/// Writes `values` seperated by commas, the way ftd saves the local position and rotation
+fn write_placement_values<W: Write>(values: &[f32], writer: &mut W) -> io::Result<()> {
+    for (i, value) in values.iter().enumerate() {
+        if i != 0 {
+            writer.write_all(b",")?;
+        }
+
+        let value = if value.is_finite() { *value } else { 0.0 };
+        write!(writer, "{value}")?;
+    }
+
+    Ok(())
+}
+
+/// Writes the `SerialisedInfo` object, see [`Breadboard::set_serialised_info`]
+fn write_serialised_info<W: Write>(serialised_info: &BTreeMap<String, String>, writer: &mut W) -> io::Result<()> {
+    writer.write_all(br#"{"JsonDictionary":{"#)?;
+    for (i, (key, value)) in serialised_info.iter().enumerate() {
+        if i != 0 {
+            writer.write_all(b",")?;
+        }
+
+        write_json_string(key, writer)?;
+        writer.write_all(b":")?;
+        write_json_string(value, writer)?;
+    }
+
+    write!(writer, r#"}},"IsEmpty":{}}}"#, serialised_info.is_empty())
+}
+
+/// Writes `s` as a quoted json string
+fn write_json_string<W: Write>(s: &str, writer: &mut W) -> io::Result<()> {
+    writer.write_all(b"\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => writer.write_all(b"\\\"")?,
+            '\\' => writer.write_all(b"\\\\")?,
+            '\n' => writer.write_all(b"\\n")?,
+            '\r' => writer.write_all(b"\\r")?,
+            '\t' => writer.write_all(b"\\t")?,
+            c if c.is_control() => write!(writer, "\\u{:04x}", c as u32)?,
+            c => write!(writer, "{c}")?,
+        }
+    }
+
+    writer.write_all(b"\"")
+}
+
+/// Writes the values of the `CSI` array, see [`CSI_LEN`]
+fn write_csi<W: Write>(writer: &mut W) -> io::Result<()> {
+    for i in 0..CSI_LEN {
+        if i != 0 {
+            writer.write_all(b",")?;
+        }
+
+        write!(writer, "{CSI_UNSET:?}")?;
+    }
+
+    Ok(())
+}
+
+/// Returns for each component if any of `outputs` is computed from it
+fn needed_components(components: &[Box<dyn Component>], outputs: &[LineInner]) -> Vec<bool> {
+    let mut needed = vec![false; components.len()];
+    let mut to_visit: Vec<usize> = outputs.iter()
+        .map(|line| line.component_index)
+        .collect();
+
+    while let Some(component_index) = to_visit.pop() {
+        if needed[component_index] {
+            continue;
+        }
+
+        needed[component_index] = true;
+        // inputs wired to missing components are reported by validate, they don't need anything
+        to_visit.extend(components[component_index].inputs()
+            .iter()
+            .map(|line| line.component_index)
+            .filter(|input_index| *input_index < components.len()));
+    }
+
+    needed
+}
+
+/// Returns a hash of each component's structure for [`Breadboard::canonical_order`]
+///
+/// The hash covers the component's kind, section data, extra entries, and the hashes of the components wired to its inputs.
+/// An input which loops back to a component whose hash is still being computed is hashed only by its output index,
+/// so for loops the hash also depends on which component of the loop was reached first.
+fn structural_hashes(components: &[Box<dyn Component>], section_overlays: &HashMap<usize, SectionData>) -> Vec<u64> {
+    // None while the hash is being computed
+    let mut hashes: Vec<Option<Option<u64>>> = vec![None; components.len()];
+
+    for root in 0..components.len() {
+        // a component is pushed a second time once its inputs are pushed, and hashed when it is popped the second time
+        let mut to_visit = vec![(root, false)];
+
+        while let Some((i, inputs_visited)) = to_visit.pop() {
+            if !inputs_visited {
+                if hashes[i].is_some() {
+                    continue;
+                }
+                hashes[i] = Some(None);
+
+                to_visit.push((i, true));
+                to_visit.extend(components[i].inputs()
+                    .iter()
+                    .rev()
+                    .map(|input| (input.component_index, false)));
+                continue;
+            }
+
+            let component = &components[i];
+            let mut section_data = component.section_data();
+            if let Some(overlay) = section_overlays.get(&i) {
+                section_data.merge(overlay);
+            }
+
+            let mut entries: Vec<_> = section_data.entries().collect();
+            entries.sort_by_key(|(id, _)| *id);
+
+            let mut hash = fnv1a(FNV_OFFSET_BASIS, component.ftd_uuid().as_bytes());
+            for (id, entry) in entries {
+                hash = fnv1a(hash, &entry.to_bytes(id));
+            }
+
+            for input in component.inputs() {
+                let input_hash = hashes[input.component_index].flatten().unwrap_or(0);
+                hash = fnv1a(hash, &input_hash.to_le_bytes());
+                hash = fnv1a(hash, &(input.output_index as u64).to_le_bytes());
+            }
+
+            hashes[i] = Some(Some(hash));
+        }
+    }
+
+    hashes.into_iter()
+        .map(|hash| hash.flatten().unwrap())
+        .collect()
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+
+/// Continues a 64 bit fnv-1a hash with `bytes`, this is used instead of std's hashers since their output can change between rust versions
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash
+}
+
+/// Clamps a component option to `[-limit, limit]`, see [`COMPONENT_VALUE_LIMIT`]
+fn clamp_setting(value: f32, limit: f32) -> f32 {
+    if value.is_nan() {
+        0.0
+    } else {
+        value.clamp(-limit, limit)
+    }
+}
+
+/// Returns the inputs of the group with only the first occurence of each line kept
+fn distinct_inputs<T: InputGroup<BNumber>>(inputs: &T) -> Vec<LineInner> {
+    let mut distinct = Vec::new();
+    for input in inputs.iter_inputs() {
+        if !distinct.contains(&input) {
+            distinct.push(input);
+        }
+    }
+
+    distinct
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Multiply {
     multiplier: f32,
     inputs: Vec<LineInner>,
@@ -481,7 +1526,7 @@ struct Multiply {
 
 impl Component for Multiply {
     fn ftd_uuid(&self) -> Uuid {
-        uuid!("930e5331-cecf-408a-8d90-dac6b479d5b0")
+        MULTIPLY_UUID
     }
 
     fn section_data(&self) -> SectionData {
@@ -496,39 +1541,104 @@ impl Component for Multiply {
     fn inputs(&self) -> &[LineInner] {
         self.inputs.as_slice()
     }
+
+    fn inputs_mut(&mut self) -> &mut [LineInner] {
+        self.inputs.as_mut_slice()
+    }
+
+    fn simulate(&self, inputs: &[SimValue], _previous_outputs: &[SimValue]) -> Option<Result<Vec<SimValue>, String>> {
+        let product = inputs.iter().try_fold(f64::from(self.multiplier), |product, input| match input {
+            SimValue::Number(n) => Ok(product * n),
+            _ => Err(String::from("multiply inputs must be numbers")),
+        });
+
+        Some(product.map(|product| vec![SimValue::Number(product)]))
+    }
 }
 
+/// Options for [`Breadboard::switch`]
+///
+/// By default the threshold is 0.5 and the open value is 0.
+/// Both values are clamped to `[-10000, 10000]` when the switch is inserted, so a larger threshold
+/// acts like a threshold of 10000 instead of keeping the switch open for every signal.
+///
+/// ```
+/// use bakery::prelude::*;
+///
+/// let breadboard = Breadboard::new();
+/// let passthrough = breadboard.constant(5.0);
+/// let signal = breadboard.constant(0.0);
+///
+/// let options = SwitchOptions::default()
+///     .with_threshold(-1.0)
+///     .with_open_value(2.0);
+/// assert_eq!(options.threshold(), -1.0);
+/// assert_eq!(options.open_value(), 2.0);
+///
+/// let _ = breadboard.switch(passthrough, signal, options);
+/// ```
 #[derive(Debug, Clone, Copy)]
 pub struct SwitchOptions {
-    threshhold: f32,
+    /// The switch signal must be above this for passthrough to be output
+    threshold: f32,
+    /// Value output while the switch is open
     open_value: f32,
 }
 
+impl SwitchOptions {
+    pub fn new(threshold: f32, open_value: f32) -> Self {
+        SwitchOptions {
+            threshold,
+            open_value,
+        }
+    }
+
+    /// Sets the value the switch signal must be above for passthrough to be output
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Sets the value output while the switch is open
+    pub fn with_open_value(mut self, open_value: f32) -> Self {
+        self.open_value = open_value;
+        self
+    }
+
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    pub fn open_value(&self) -> f32 {
+        self.open_value
+    }
+}
+
 impl Default for SwitchOptions {
     fn default() -> Self {
         SwitchOptions {
-            threshhold: 0.5,
+            threshold: 0.5,
             open_value: 0.0,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Switch {
     // first input is passthrough, second is switch signal
     inputs: [LineInner; 2],
-    threshhold: f32,
+    threshold: f32,
     open_value: f32,
 }
 
 impl Component for Switch {
     fn ftd_uuid(&self) -> Uuid {
-        uuid!("581de01e-3754-45f6-9133-f51443844eca")
+        SWITCH_UUID
     }
 
     fn section_data(&self) -> SectionData {
         SectionData::default()
-            .with_entry(0, DataEntry::F32(self.threshhold))
+            .with_entry(0, DataEntry::F32(self.threshold))
             .with_entry(1, DataEntry::F32(self.open_value))
     }
 
@@ -539,4 +1649,504 @@ impl Component for Switch {
     fn inputs(&self) -> &[LineInner] {
         &self.inputs
     }
-}
\ No newline at end of file
+
+    fn inputs_mut(&mut self) -> &mut [LineInner] {
+        &mut self.inputs
+    }
+
+    fn simulate(&self, inputs: &[SimValue], _previous_outputs: &[SimValue]) -> Option<Result<Vec<SimValue>, String>> {
+        let [SimValue::Number(passthrough), SimValue::Number(switch_signal)] = inputs else {
+            return Some(Err(String::from("switch inputs must be numbers")));
+        };
+
+        let output = if *switch_signal > f64::from(self.threshold) {
+            *passthrough
+        } else {
+            self.open_value.into()
+        };
+
+        Some(Ok(vec![SimValue::Number(output)]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::uuid;
+
+    #[test]
+    fn csi_array_length() {
+        let mut blueprint = Vec::new();
+        Breadboard::new().write_blueprint(&mut blueprint).unwrap();
+        let blueprint = String::from_utf8(blueprint).unwrap();
+
+        let csi_start = blueprint.find(r#""CSI":["#).unwrap() + r#""CSI":["#.len();
+        let csi_len = blueprint[csi_start..].find(']').unwrap();
+        let values: Vec<&str> = blueprint[csi_start..csi_start + csi_len].split(',').collect();
+
+        assert_eq!(values.len(), 80);
+        assert!(values.iter().all(|value| *value == "-1.0"));
+    }
+
+    #[test]
+    fn special_sections_present() {
+        let breadboard = Breadboard::new();
+        let _ = breadboard.position();
+        let data = breadboard.block_data().unwrap();
+
+        for section_id in EMPTY_SECTION_IDS {
+            assert!(data.section_data(section_id.into()).unwrap().is_empty(), "section {section_id}");
+        }
+
+        let main_section = data.section_data(MAIN_SECTION_ID.into()).unwrap();
+        assert!(main_section.entry(0).is_some());
+        assert!(main_section.entry(1).is_some());
+    }
+
+    #[test]
+    fn input_group_access() {
+        let breadboard = Breadboard::new();
+        let lines = breadboard.constants(&[1.0, 2.0, 3.0]);
+        let [a, b, c] = [&lines[0], &lines[1], &lines[2]].map(|line| line.inner);
+
+        let tuple = (lines[0].clone(), lines[1].clone(), lines[2].clone());
+        assert_eq!(InputGroup::len(&tuple), 3);
+        assert_eq!(InputGroup::get(&tuple, 1), Some(b));
+        assert_eq!(InputGroup::get(&tuple, 3), None);
+        assert_eq!(InputGroup::last(&tuple), Some(c));
+
+        let array = [lines[1].clone(), lines[0].clone()];
+        assert_eq!(InputGroup::len(&array[..]), 2);
+        assert_eq!(InputGroup::first(&array[..]), Some(b));
+        assert_eq!(InputGroup::last(&array[..]), Some(a));
+
+        let empty: &[Line<BNumber>] = &[];
+        assert!(InputGroup::is_empty(empty));
+        assert_eq!(InputGroup::first(empty), None);
+        assert_eq!(InputGroup::last(empty), None);
+
+        assert_eq!(InputGroup::len(&lines[2]), 1);
+        assert_eq!(InputGroup::last(&lines[2]), Some(c));
+    }
+
+    #[test]
+    fn boolean_constants() {
+        let breadboard = Breadboard::new();
+        let b_true = breadboard.b_true();
+        let b_false = breadboard.b_false();
+
+        let mut simulation = Simulation::new(&breadboard);
+        simulation.step().unwrap();
+        assert_eq!(simulation.number(&b_true).unwrap(), 1.0);
+        assert_eq!(simulation.number(&b_false).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn output_use_counts() {
+        let breadboard = Breadboard::new();
+        let a = breadboard.constant(1.0);
+        let b = breadboard.constant(2.0);
+        let sum = &a + &b;
+        let _ = &sum * &a;
+        let _ = breadboard.switch(a.clone(), a, SwitchOptions::default());
+
+        assert_eq!(breadboard.output_use_counts(), [vec![4], vec![1], vec![1], vec![0], vec![0]]);
+    }
+
+    #[test]
+    fn component_bytes_round_trip() {
+        let breadboard = Breadboard::new();
+        let constant = breadboard.constant(1.0);
+        let blob: Vec<u8> = (0..300).map(|i| (i % 251) as u8).collect();
+        breadboard.set_component_bytes(&constant, 40, blob.clone());
+
+        let data = breadboard.block_data().unwrap();
+        let section = data.section_data(COMPONENT_ID_START.into()).unwrap();
+        assert!(matches!(section.entry(40), Some(DataEntry::Bytes(bytes)) if *bytes == blob));
+
+        // entry id, length, and the bytes of each chunk
+        let mut expected = vec![40, 0, 255];
+        expected.extend_from_slice(&blob[..255]);
+        expected.extend_from_slice(&[40, 0, 45]);
+        expected.extend_from_slice(&blob[255..]);
+
+        let serialized = data.serialize(0.into()).unwrap();
+        assert!(serialized.windows(expected.len()).any(|window| window == expected));
+    }
+
+    #[test]
+    fn material_cost() {
+        let breadboard = Breadboard::new();
+        assert!(breadboard.to_blueprint_string().unwrap()
+            .contains(r#""SavedMaterialCost":10.0,"ContainedMaterialCost":0.0,"#));
+
+        breadboard.set_material_cost(52.5);
+        assert!(breadboard.to_blueprint_string().unwrap()
+            .contains(r#""SavedMaterialCost":52.5,"ContainedMaterialCost":0.0,"#));
+    }
+
+    #[test]
+    fn placement() {
+        let breadboard = Breadboard::new();
+        assert!(breadboard.to_blueprint_string().unwrap()
+            .contains(r#""LocalPosition":"0,0,0","LocalRotation":"0,0,0,1","ForceId""#));
+
+        breadboard.set_local_position(1.5, -2.0, f32::NAN);
+        breadboard.set_local_rotation(0.0, 0.70710677, 0.0, 0.70710677);
+        assert!(breadboard.to_blueprint_string().unwrap()
+            .contains(r#""LocalPosition":"1.5,-2,0","LocalRotation":"0,0.70710677,0,0.70710677","ForceId""#));
+    }
+
+    #[test]
+    fn item_number_and_persistent_indices() {
+        let breadboard = Breadboard::new();
+        let blueprint = breadboard.to_blueprint_string().unwrap();
+        assert!(blueprint.contains(r#""Name":null,"ItemNumber":0,"LocalPosition""#));
+        assert!(blueprint.contains(r#""PersistentSubObjectIndex":-1,"PersistentBlockIndex":-1,"AuthorDetails""#));
+
+        breadboard.set_item_number(3);
+        breadboard.set_persistent_sub_object_index(12);
+        breadboard.set_persistent_block_index(40);
+        assert_eq!(breadboard.item_number(), 3);
+
+        let blueprint = breadboard.to_blueprint_string().unwrap();
+        assert!(blueprint.contains(r#""Name":null,"ItemNumber":3,"LocalPosition""#));
+        assert!(blueprint.contains(r#""PersistentSubObjectIndex":12,"PersistentBlockIndex":40,"AuthorDetails""#));
+    }
+
+    #[test]
+    fn serialised_info_escaping() {
+        let breadboard = Breadboard::new();
+        assert!(breadboard.to_blueprint_string().unwrap()
+            .contains(r#""SerialisedInfo":{"JsonDictionary":{},"IsEmpty":true},"#));
+
+        breadboard.set_serialised_info("a \"key\"", "{\"x\":\"C:\\path\"}\n\u{1}");
+        breadboard.set_serialised_info("b", "");
+        let blueprint = breadboard.to_blueprint_string().unwrap();
+
+        let expected = r#""SerialisedInfo":{"JsonDictionary":{"a \"key\"":"{\"x\":\"C:\\path\"}\n\u0001","b":""},"IsEmpty":false},"Name":null"#;
+        assert!(blueprint.contains(expected), "{blueprint}");
+    }
+
+    #[test]
+    fn expression_too_long() {
+        let breadboard = Breadboard::new();
+        let altitude = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        // balanced so the expression isn't nested deep enough to overflow the stack
+        fn sum_of_ones(count: usize) -> EvaluatorExpression {
+            if count == 1 {
+                EvaluatorExpression::Int(1)
+            } else {
+                sum_of_ones(count / 2) + sum_of_ones(count - count / 2)
+            }
+        }
+
+        let _sum: Line<BNumber> = breadboard.evaluator_expr(altitude.clone(), |a| {
+            *a + sum_of_ones(MAX_EVALUATOR_EXPRESSION_LENGTH / 4)
+        });
+
+        assert!(matches!(
+            breadboard.to_blueprint_string(),
+            Err(BreadboardError::ExpressionTooLong { component_index: 1, length }) if length > MAX_EVALUATOR_EXPRESSION_LENGTH,
+        ));
+
+        // the guard only applies to saved components
+        breadboard.mark_output(&altitude);
+        breadboard.prune_unused();
+        assert!(breadboard.to_blueprint_string().is_ok());
+    }
+
+    #[test]
+    fn prune_keeps_only_ancestors_of_outputs() {
+        let breadboard = Breadboard::new();
+        let altitude = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let unused = breadboard.constant(5.0);
+        let offset = breadboard.constant(2.0);
+        let sum = &altitude + &offset;
+        let _dead_end = &unused + &altitude;
+        breadboard.mark_output(&sum);
+
+        assert_eq!(breadboard.unused_components(), [1, 4]);
+
+        breadboard.prune_unused();
+        let imported = Breadboard::from_blueprint(&breadboard.blueprint_data().unwrap()).unwrap();
+        let components = imported.0.components.borrow();
+        let saved: Vec<_> = components.iter()
+            .map(|component| (component.ftd_uuid(), component.inputs().to_vec()))
+            .collect();
+
+        // the saved components are renumbered, and the wiring follows them
+        assert_eq!(saved, [
+            (ALTITUDE_UUID, Vec::new()),
+            (CONSTANT_UUID, Vec::new()),
+            (EVALUATOR_UUID, vec![LineInner { component_index: 0, output_index: 0 }, LineInner { component_index: 1, output_index: 0 }]),
+        ]);
+        // pruning only changes what is saved
+        assert_eq!(breadboard.component_count(), 5);
+    }
+
+    #[test]
+    fn merge_offsets_lines() {
+        let breadboard = Breadboard::new();
+        let _ = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let _ = breadboard.constant(1.0);
+
+        let other = Breadboard::new();
+        let altitude = other.altitude(AltitudeOutputType::SeaLevel);
+        let offset = other.constant(3.0);
+        let sum = &altitude + &offset;
+        other.mark_output(&sum);
+        other.set_component_entry(&offset, 7, DataEntry::Bool(true));
+
+        let merged = breadboard.merge(&other);
+        let merged_sum = merged.line(&sum);
+
+        assert_eq!(merged_sum.inner, LineInner { component_index: 4, output_index: 0 });
+        assert_eq!(breadboard.component_count(), 5);
+        assert_eq!(
+            breadboard.0.components.borrow()[4].inputs(),
+            [LineInner { component_index: 2, output_index: 0 }, LineInner { component_index: 3, output_index: 0 }],
+        );
+        assert_eq!(*breadboard.0.outputs.borrow(), [merged_sum.inner]);
+        assert!(breadboard.0.section_overlays.borrow()[&3].entry(7).is_some());
+        // the merged breadboard is unchanged
+        assert_eq!(other.0.components.borrow()[2].inputs(), [altitude.inner, offset.inner]);
+
+        let mut simulation = Simulation::new(&breadboard);
+        simulation.set_number(&Line::new(breadboard.clone(), 0, 0), 0.0);
+        simulation.set_number(&merged.line(&altitude), 4.0);
+        simulation.step().unwrap();
+        assert_eq!(simulation.number(&merged_sum), Some(7.0));
+    }
+
+    #[test]
+    fn canonical_order_ignores_insertion_order() {
+        let saved_expressions = |breadboard: &Breadboard| {
+            breadboard.canonical_order();
+            let data = breadboard.block_data().unwrap();
+
+            let main_section = data.section_data(MAIN_SECTION_ID.into()).unwrap();
+            (0..3)
+                .map(|i| {
+                    let Some(DataEntry::Uuid(uuid)) = main_section.entry(2 * i) else {
+                        panic!("component {i} has no uuid");
+                    };
+                    let section = data.section_data(SectionId::new(COMPONENT_ID_START + u32::from(i))).unwrap();
+                    let expr = match section.entry(0) {
+                        Some(DataEntry::String(expr)) => Some(expr.clone()),
+                        _ => None,
+                    };
+
+                    (*uuid, expr)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let a = Breadboard::new();
+        let altitude = a.altitude(AltitudeOutputType::SeaLevel);
+        let constant = a.constant(2.0);
+        let _ = &altitude * &constant;
+
+        let b = Breadboard::new();
+        let constant = b.constant(2.0);
+        let altitude = b.altitude(AltitudeOutputType::SeaLevel);
+        let _ = &altitude * &constant;
+
+        assert_eq!(saved_expressions(&a), saved_expressions(&b));
+    }
+
+    #[test]
+    fn canonical_order_wire_uuids_are_stable() {
+        // the wiring, outputs and expression of each saved component, leaving out its id and position
+        let saved_components = |breadboard: &Breadboard| {
+            let data = breadboard.block_data().unwrap();
+            let count = breadboard.component_count() as u32;
+
+            (0..count)
+                .map(|i| {
+                    let section = data.section_data(SectionId::new(COMPONENT_ID_START + i)).unwrap();
+                    [0, 900, 901].map(|id| section.entry(id).map(|entry| entry.to_bytes(id)))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let build = |extra: bool| {
+            let breadboard = Breadboard::new();
+            breadboard.canonical_order();
+            let altitude = breadboard.altitude(AltitudeOutputType::SeaLevel);
+            if extra {
+                let _ = breadboard.constant(5.0);
+            }
+            let offset = breadboard.constant(2.0);
+            let _ = &altitude + &offset;
+            let _ = &altitude * &offset;
+            breadboard
+        };
+
+        let breadboard = build(false);
+        let saved = saved_components(&breadboard);
+        assert_eq!(saved, saved_components(&breadboard));
+
+        let with_extra = saved_components(&build(true));
+        assert_eq!(with_extra.len(), saved.len() + 1);
+        for component in saved.iter() {
+            assert!(with_extra.contains(component));
+        }
+    }
+
+    #[test]
+    fn numeric_clamping() {
+        let breadboard = Breadboard::new();
+        let one = breadboard.constant(1.0);
+        let lines = [
+            (breadboard.constant(20000.0), 10000.0),
+            (breadboard.constant(-20000.0), -10000.0),
+            (breadboard.constant(f32::NAN), 0.0),
+            (breadboard.multiply(&one, 500.0), 100.0),
+            (breadboard.multiply(&one, -500.0), -100.0),
+            (breadboard.switch(one.clone(), one.clone(), SwitchOptions::default().with_threshold(20000.0)), 0.0),
+            (breadboard.switch(one.clone(), one.clone(), SwitchOptions::default().with_open_value(-20000.0)), 1.0),
+            (breadboard.switch(one.clone(), -&one, SwitchOptions::default().with_open_value(-20000.0)), -10000.0),
+            (breadboard.precise_constant(20000.0), 20000.0),
+            (breadboard.new_vector(20000.0, 0.0, 0.0).x(), 20000.0),
+            (breadboard.precise_constant(f64::INFINITY), f32::MAX.into()),
+            (breadboard.precise_constant(f64::NAN), 0.0),
+        ];
+
+        let mut simulation = Simulation::new(&breadboard);
+        simulation.step().unwrap();
+        for (i, (line, expected)) in lines.iter().enumerate() {
+            assert_eq!(simulation.number(line), Some(*expected), "line {i}");
+        }
+
+        let random = breadboard.random_number(-20000.0, -30000.0);
+        let components = breadboard.0.components.borrow();
+        let Some(DataEntry::Vector2(range)) = components[random.inner.component_index].section_data().entry(0).cloned() else {
+            panic!("random input has no range");
+        };
+        assert_eq!(range, Vector2::new(-10000.0, -10000.0));
+    }
+
+    #[test]
+    fn target_info_outputs_by_index() {
+        let target = Breadboard::new().target_info();
+
+        assert_eq!(target.number_output(1).unwrap().inner, target.distance.inner);
+        assert_eq!(target.vector_output(5).unwrap().inner, target.velocity.inner);
+        assert!(target.vector_output(1).is_none());
+        assert!(target.number_output(4).is_none());
+        assert!(target.number_output(7).is_none());
+    }
+
+    #[test]
+    fn validate_reports_every_problem() {
+        let breadboard = Breadboard::new();
+        assert_eq!(breadboard.validate(), Ok(()));
+
+        let altitude = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let _unused = breadboard.constant(1.0);
+        breadboard.mark_output(&altitude);
+        let missing = LineInner { component_index: 10, output_index: 0 };
+        breadboard.insert_component(Multiply { multiplier: 1.0, inputs: vec![altitude.inner, missing] });
+        breadboard.insert_component(Multiply { multiplier: 1.0, inputs: vec![altitude.inner; MAX_MULTIPLY_INPUTS + 1] });
+
+        let errors = breadboard.validate().unwrap_err();
+        assert_eq!(errors, [
+            ValidationError::UnusedComponent { component_index: 1 },
+            ValidationError::InvalidInput { component_index: 2, input_index: 1, line: missing },
+            ValidationError::UnusedComponent { component_index: 2 },
+            ValidationError::ManyMultiplyInputs { component_index: 3, count: MAX_MULTIPLY_INPUTS + 1 },
+            ValidationError::UnusedComponent { component_index: 3 },
+        ]);
+        assert_eq!(errors.iter().filter(|error| error.is_warning()).count(), 4);
+
+        // pruned components are not saved, so only the problems of saved components are left
+        breadboard.prune_unused();
+        assert_eq!(breadboard.validate(), Ok(()));
+    }
+
+    #[test]
+    fn component_limit() {
+        assert_eq!(MAX_COMPONENTS, 9359);
+
+        let breadboard = Breadboard::new();
+        breadboard.repeat(MAX_COMPONENTS, |i, breadboard| breadboard.constant(i as f32));
+        assert!(breadboard.to_blueprint_string().is_ok());
+
+        breadboard.constant(0.0);
+        assert_eq!(
+            breadboard.to_blueprint_string(),
+            Err(BreadboardError::TooManyComponents { count: MAX_COMPONENTS + 1 }),
+        );
+    }
+
+    #[test]
+    fn nested_insertion_is_an_error() {
+        let result = Breadboard::try_build(|breadboard| {
+            let altitude = breadboard.altitude(AltitudeOutputType::SeaLevel);
+
+            // stands in for a breadboard method which reads its components while building another component
+            let _components = breadboard.0.components.borrow();
+            breadboard.try_switch(altitude.clone(), altitude, SwitchOptions::default())?;
+            Ok(())
+        });
+
+        assert!(matches!(result, Err(BreadboardError::ComponentsBorrowed)));
+    }
+
+    #[test]
+    fn failed_save_keeps_existing_file() {
+        let path = std::env::temp_dir().join(format!("bakery_failed_save_{}.blueprint", std::process::id()));
+        std::fs::write(&path, "existing prefab").unwrap();
+
+        let breadboard = Breadboard::new();
+        breadboard.repeat(MAX_COMPONENTS + 1, |_, breadboard| breadboard.constant(0.0));
+        let error = breadboard.save_to_prefab_file(&path).unwrap_err();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(contents, "existing prefab");
+    }
+
+    #[test]
+    fn validate_component_limit() {
+        let breadboard = Breadboard::new();
+        breadboard.repeat(MAX_COMPONENTS, |i, breadboard| breadboard.constant(i as f32));
+        assert_eq!(breadboard.validate(), Ok(()));
+
+        breadboard.constant(0.0);
+        assert_eq!(
+            breadboard.validate(),
+            Err(vec![ValidationError::Breadboard(BreadboardError::TooManyComponents { count: MAX_COMPONENTS + 1 })]),
+        );
+
+        // the limit is on saved components, so pruning unneeded ones brings the breadboard back under it
+        let output = breadboard.constant(0.0);
+        breadboard.mark_output(&output);
+        breadboard.prune_unused();
+        assert_eq!(breadboard.validate(), Ok(()));
+    }
+
+    #[test]
+    fn component_uuids() {
+        let components: [(Box<dyn Component>, Uuid); 10] = [
+            (Box::new(Constant { n: 0.0 }), uuid!("9142c70d-7833-41cd-804d-554e990b6904")),
+            (Box::new(RandomInput { min: 0.0, max: 1.0 }), uuid!("268b7db2-bccf-41fd-8cfa-3f21d2f2bacb")),
+            (Box::new(Altitude { typ: AltitudeOutputType::SeaLevel }), uuid!("ae46572b-dff8-4153-97dc-146108f3a64f")),
+            (Box::new(Position), uuid!("e20d6a3a-c0b9-4665-8749-7a85c40afabe")),
+            (Box::new(Speed { typ: SpeedOutputType::Magnitude }), uuid!("c8f64443-b81f-4b75-8105-18cd6e453539")),
+            // velocity is the vector mode of the speed component
+            (Box::new(Velocity { typ: VelocityOutputType::Magnitude }), uuid!("c8f64443-b81f-4b75-8105-18cd6e453539")),
+            (Box::new(TargetInfo), uuid!("5390bcf0-d09d-40b8-99a3-8d3752e656c6")),
+            (Box::new(Multiply { multiplier: 1.0, inputs: Vec::new() }), uuid!("930e5331-cecf-408a-8d90-dac6b479d5b0")),
+            (Box::new(Switch { inputs: [LineInner { component_index: 0, output_index: 0 }; 2], threshold: 0.5, open_value: 0.0 }), uuid!("581de01e-3754-45f6-9133-f51443844eca")),
+            (Box::new(evaluator::Evaluator::default()), uuid!("7cf3b706-757e-428a-bb45-454a17ed710a")),
+        ];
+
+        for (component, expected_uuid) in components {
+            assert_eq!(component.ftd_uuid(), expected_uuid);
+            assert!(COMPONENT_COSTS.iter().any(|(uuid, _)| *uuid == expected_uuid), "{expected_uuid} has no cost");
+        }
+    }
+}
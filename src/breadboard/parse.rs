@@ -0,0 +1,590 @@
+//! Parser for ftd evaluator expression strings
+//!
+//! Binary operators from lowest to highest precedence are `or`, `|`, `&`, comparisons,
+//! `+ -` and `* / % x`, all left associative. Prefix `!` and `-` bind tighter than any binary operator,
+//! and `.property` postfixes bind tighter than prefix operators.
+
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use super::evaluator::EvaluatorExpression;
+
+/// Error returned when an evaluator expression string could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Byte offset in the expression string where the error was found
+    position: usize,
+    message: String,
+}
+
+impl ParseError {
+    fn new(position: usize, message: impl Into<String>) -> Self {
+        ParseError {
+            position,
+            message: message.into(),
+        }
+    }
+
+    /// Byte offset in the expression string where the error was found
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl Error for ParseError {}
+
+/// Punctuation and operator tokens, longer symbols come first so they are matched before their prefixes
+const SYMBOLS: &[&str] = &[
+    "!=", ">=", "<=", "+", "-", "*", "/", "%", "=", ">", "<", "!", "&", "|", "(", ")", ",", ".",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token<'a> {
+    Int(i64),
+    Float(f64),
+    Ident(&'a str),
+    Symbol(&'static str),
+    End,
+}
+
+impl Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Int(n) => write!(f, "`{n}`"),
+            Self::Float(n) => write!(f, "`{n}`"),
+            Self::Ident(ident) => write!(f, "`{ident}`"),
+            Self::Symbol(symbol) => write!(f, "`{symbol}`"),
+            Self::End => write!(f, "end of expression"),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<(Token<'_>, usize)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut rest = s;
+
+    loop {
+        let trimmed = rest.trim_start();
+        let position = s.len() - trimmed.len();
+        rest = trimmed;
+
+        let Some(c) = rest.chars().next() else {
+            tokens.push((Token::End, position));
+            return Ok(tokens);
+        };
+
+        let len = if c.is_ascii_digit() {
+            let mut len = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+
+            // only a dot followed by a digit is part of the number, otherwise it starts a property
+            let is_float = rest[len..].starts_with('.')
+                && rest[len + 1..].starts_with(|c: char| c.is_ascii_digit());
+            if is_float {
+                len += 1;
+                len += rest[len..].find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len() - len);
+            }
+
+            let number = &rest[..len];
+            let token = if is_float {
+                number.parse().map(Token::Float).ok()
+            } else {
+                number.parse().map(Token::Int).ok()
+            };
+
+            tokens.push((token.ok_or_else(|| ParseError::new(position, format!("invalid number `{number}`")))?, position));
+            len
+        } else if c.is_ascii_alphabetic() {
+            let len = rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len());
+            tokens.push((Token::Ident(&rest[..len]), position));
+            len
+        } else if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(**symbol)) {
+            tokens.push((Token::Symbol(symbol), position));
+            symbol.len()
+        } else {
+            return Err(ParseError::new(position, format!("unexpected character `{c}`")));
+        };
+
+        rest = &rest[len..];
+    }
+}
+
+type BinaryFn = fn(Box<EvaluatorExpression>, Box<EvaluatorExpression>) -> EvaluatorExpression;
+
+/// Returns the precedence and constructor of a binary operator, higher precedence binds tighter
+fn binary_operator(token: Token) -> Option<(u8, BinaryFn)> {
+    let operator: (u8, BinaryFn) = match token {
+        Token::Ident("or") => (1, EvaluatorExpression::FalseCoalesce),
+        Token::Symbol("|") => (2, EvaluatorExpression::OpOr),
+        Token::Symbol("&") => (3, EvaluatorExpression::OpAnd),
+        Token::Symbol("=") => (4, EvaluatorExpression::Eq),
+        Token::Symbol("!=") => (4, EvaluatorExpression::Ne),
+        Token::Symbol(">") => (4, EvaluatorExpression::Gt),
+        Token::Symbol(">=") => (4, EvaluatorExpression::Gte),
+        Token::Symbol("<") => (4, EvaluatorExpression::Lt),
+        Token::Symbol("<=") => (4, EvaluatorExpression::Lte),
+        Token::Symbol("+") => (5, EvaluatorExpression::Add),
+        Token::Symbol("-") => (5, EvaluatorExpression::Sub),
+        Token::Symbol("*") => (6, EvaluatorExpression::Mul),
+        Token::Symbol("/") => (6, EvaluatorExpression::Div),
+        Token::Symbol("%") => (6, EvaluatorExpression::Mod),
+        Token::Ident("x") => (6, EvaluatorExpression::Cross),
+        _ => return None,
+    };
+
+    Some(operator)
+}
+
+/// Builds the expression for a function call, or returns `None` if there is no function with this name and argument count
+///
+/// Function names are matched case insensitively.
+fn function_call(name: &str, args: Vec<EvaluatorExpression>) -> Option<EvaluatorExpression> {
+    use EvaluatorExpression as E;
+
+    let name = name.to_ascii_lowercase();
+    let arg_count = args.len();
+    let mut args = args.into_iter().map(Box::new);
+    let mut arg = || args.next().unwrap();
+
+    let expr = match (name.as_str(), arg_count) {
+        ("sin", 1) => E::Sin(arg()),
+        ("cos", 1) => E::Cos(arg()),
+        ("tan", 1) => E::Tan(arg()),
+        ("sqrt", 1) => E::Sqrt(arg()),
+        ("asin", 1) => E::Asin(arg()),
+        ("acos", 1) => E::Acos(arg()),
+        ("atan", 1) => E::Atan(arg()),
+        ("atan", 2) => E::Atan2(arg(), arg()),
+        ("exp", 1) => E::Exp(arg()),
+        ("log", 1) => E::Log(arg()),
+        ("pow", 2) => E::Pow(arg(), arg()),
+        ("abs", 1) => E::Abs(arg()),
+        ("sign", 1) => E::Sign(arg()),
+        ("round", 1) => E::Round(arg()),
+        ("floor", 1) => E::Floor(arg()),
+        ("ceil", 1) => E::Ceil(arg()),
+        ("max", 1) => E::MaxV(arg()),
+        ("max", 2) => E::Max2(arg(), arg()),
+        ("max", 3) => E::Max3(arg(), arg(), arg()),
+        ("min", 1) => E::MinV(arg()),
+        ("min", 2) => E::Min2(arg(), arg()),
+        ("min", 3) => E::Min3(arg(), arg(), arg()),
+        ("if", 3) => E::If {
+            condition: arg(),
+            true_value: arg(),
+            false_value: arg(),
+        },
+        ("vector", 3) => E::Vector(arg(), arg(), arg()),
+        ("fromtorot", 2) => E::MakeRotationBetween {
+            from_vector: arg(),
+            to_vector: arg(),
+        },
+        ("fromeuler", 1) => E::FromEularV(arg()),
+        ("fromeuler", 3) => E::FromEuler {
+            pitch: arg(),
+            yaw: arg(),
+            roll: arg(),
+        },
+        ("toeuler", 1) => E::ToEularV(arg()),
+        ("angle", 1) => E::Angle(arg()),
+        ("angle", 2) => E::AngleBetween {
+            from_vector: arg(),
+            to_vector: arg(),
+        },
+        ("axis", 1) => E::Axis(arg()),
+        ("setx", 2) => E::SetX {
+            vector: arg(),
+            x: arg(),
+        },
+        ("sety", 2) => E::SetY {
+            vector: arg(),
+            y: arg(),
+        },
+        ("setz", 2) => E::SetZ {
+            vector: arg(),
+            z: arg(),
+        },
+        ("outputv", 1) => E::OutputV(arg()),
+        ("output", 1) => E::Output(arg()),
+        _ => return None,
+    };
+
+    Some(expr)
+}
+
+/// Builds the expression for a `.property` postfix, or returns `None` if there is no such property
+fn property(name: &str, val: EvaluatorExpression) -> Option<EvaluatorExpression> {
+    let val = Box::new(val);
+
+    let expr = match name {
+        "x" => EvaluatorExpression::GetX(val),
+        "y" => EvaluatorExpression::GetY(val),
+        "z" => EvaluatorExpression::GetZ(val),
+        "magnitude" => EvaluatorExpression::Magnitude(val),
+        "sqrMagnitude" => EvaluatorExpression::SquareMagnitude(val),
+        "inverse" => EvaluatorExpression::RotationInverse(val),
+        _ => return None,
+    };
+
+    Some(expr)
+}
+
+struct Parser<'a> {
+    tokens: Vec<(Token<'a>, usize)>,
+    index: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(s: &'a str) -> Result<Self, ParseError> {
+        Ok(Parser {
+            tokens: tokenize(s)?,
+            index: 0,
+        })
+    }
+
+    // the last token is always `End`, and reading past it keeps returning `End`
+    fn current(&self) -> (Token<'a>, usize) {
+        self.tokens[self.index.min(self.tokens.len() - 1)]
+    }
+
+    fn peek(&self) -> Token<'a> {
+        self.current().0
+    }
+
+    fn position(&self) -> usize {
+        self.current().1
+    }
+
+    fn next(&mut self) -> Token<'a> {
+        let token = self.peek();
+        self.index += 1;
+        token
+    }
+
+    fn unexpected(&self) -> ParseError {
+        ParseError::new(self.position(), format!("unexpected {}", self.peek()))
+    }
+
+    fn expect(&mut self, symbol: &'static str) -> Result<(), ParseError> {
+        if self.peek() == Token::Symbol(symbol) {
+            self.next();
+            Ok(())
+        } else {
+            Err(ParseError::new(self.position(), format!("expected `{symbol}`, found {}", self.peek())))
+        }
+    }
+
+    /// Parses comma seperated expressions until the end of the string
+    fn parse_list(&mut self) -> Result<Vec<EvaluatorExpression>, ParseError> {
+        let mut exprs = Vec::new();
+        if self.peek() == Token::End {
+            return Ok(exprs);
+        }
+
+        loop {
+            exprs.push(self.parse_binary(0)?);
+
+            match self.next() {
+                Token::Symbol(",") => (),
+                Token::End => return Ok(exprs),
+                _ => {
+                    self.index -= 1;
+                    return Err(self.unexpected());
+                },
+            }
+        }
+    }
+
+    /// Parses a chain of binary operators which all have a precedence above `min_precedence`
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<EvaluatorExpression, ParseError> {
+        let mut lhs = self.parse_unary()?;
+
+        while let Some((precedence, operator_fn)) = binary_operator(self.peek()) {
+            if precedence <= min_precedence {
+                break;
+            }
+
+            self.next();
+            let rhs = self.parse_binary(precedence)?;
+            lhs = operator_fn(Box::new(lhs), Box::new(rhs));
+        }
+
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<EvaluatorExpression, ParseError> {
+        match self.peek() {
+            Token::Symbol("!") => {
+                self.next();
+                Ok(!self.parse_unary()?)
+            },
+            Token::Symbol("-") => {
+                self.next();
+
+                // a minus directly before a number is part of the number
+                let negative_literal = match self.peek() {
+                    Token::Int(n) => Some(EvaluatorExpression::Int(-n)),
+                    Token::Float(n) => Some(EvaluatorExpression::Float(-n)),
+                    _ => None,
+                };
+
+                match negative_literal {
+                    Some(literal) => {
+                        self.next();
+                        self.parse_postfix(literal)
+                    },
+                    None => Ok(-self.parse_unary()?),
+                }
+            },
+            _ => {
+                let primary = self.parse_primary()?;
+                self.parse_postfix(primary)
+            },
+        }
+    }
+
+    fn parse_postfix(&mut self, mut expr: EvaluatorExpression) -> Result<EvaluatorExpression, ParseError> {
+        while self.peek() == Token::Symbol(".") {
+            self.next();
+
+            let position = self.position();
+            let Token::Ident(name) = self.next() else {
+                self.index -= 1;
+                return Err(ParseError::new(position, format!("expected property name, found {}", self.peek())));
+            };
+
+            expr = property(name, expr)
+                .ok_or_else(|| ParseError::new(position, format!("unknown property `{name}`")))?;
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_primary(&mut self) -> Result<EvaluatorExpression, ParseError> {
+        let position = self.position();
+
+        match self.next() {
+            Token::Int(n) => Ok(EvaluatorExpression::Int(n)),
+            Token::Float(n) => Ok(EvaluatorExpression::Float(n)),
+            Token::Symbol("(") => {
+                let expr = self.parse_binary(0)?;
+                self.expect(")")?;
+                Ok(expr)
+            },
+            Token::Ident(name) if self.peek() == Token::Symbol("(") => {
+                self.next();
+
+                let mut args = Vec::new();
+                if self.peek() != Token::Symbol(")") {
+                    loop {
+                        args.push(self.parse_binary(0)?);
+
+                        if self.peek() == Token::Symbol(",") {
+                            self.next();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                self.expect(")")?;
+
+                let arg_count = args.len();
+                function_call(name, args).ok_or_else(|| {
+                    ParseError::new(position, format!("unknown function `{name}` with {arg_count} arguments"))
+                })
+            },
+            Token::Ident("a") => Ok(EvaluatorExpression::InputA),
+            Token::Ident("b") => Ok(EvaluatorExpression::InputB),
+            Token::Ident("c") => Ok(EvaluatorExpression::InputC),
+            Token::Ident("d") => Ok(EvaluatorExpression::InputD),
+            Token::Ident("e") => Ok(EvaluatorExpression::InputE),
+            Token::Ident(name) => Err(ParseError::new(position, format!("unknown input `{name}`"))),
+            _ => {
+                self.index -= 1;
+                Err(self.unexpected())
+            },
+        }
+    }
+}
+
+impl EvaluatorExpression {
+    /// Parses an expression written in ftd's evaluator syntax, such as `Sin(a) * (b + 3)` or `(v).magnitude`
+    ///
+    /// Everything the [`Display`] impl writes can be parsed, and gives back an equal expression.
+    /// Function names are case insensitive, but input names and properties are not.
+    pub fn parse(s: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser::new(s)?;
+        let expr = parser.parse_binary(0)?;
+
+        if parser.peek() == Token::End {
+            Ok(expr)
+        } else {
+            Err(parser.unexpected())
+        }
+    }
+}
+
+/// Parses the comma seperated expressions of an evaluator with several outputs
+pub(super) fn parse_expression_list(s: &str) -> Result<Vec<EvaluatorExpression>, ParseError> {
+    Parser::new(s)?.parse_list()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use EvaluatorExpression as E;
+
+    fn a() -> E {
+        E::InputA
+    }
+
+    fn b() -> E {
+        E::InputB
+    }
+
+    fn c() -> E {
+        E::InputC
+    }
+
+    fn parse(s: &str) -> E {
+        E::parse(s).unwrap_or_else(|err| panic!("failed to parse `{s}`: {err}"))
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let exprs = [
+            E::Int(3),
+            E::Int(-3),
+            E::Float(2.5),
+            E::Float(-0.125),
+            E::Float(4.0),
+            E::Float(-4.0),
+            -E::Int(3),
+            -(a() + b()),
+            !(a() - E::Float(1.5)),
+            a() * b() + c() / E::Int(2) % E::Int(3),
+            a() - (b() - c()),
+            E::Cross(Box::new(a()), Box::new(b())) * c(),
+            E::FalseCoalesce(
+                Box::new(E::OpOr(Box::new(a()), Box::new(E::OpAnd(Box::new(b()), Box::new(c()))))),
+                Box::new(E::Int(0)),
+            ),
+            E::Eq(Box::new(a() - a()), Box::new(E::Int(0))),
+            E::Ne(Box::new(a()), Box::new(b())),
+            E::Gte(Box::new(a()), Box::new(E::Float(-1.5))),
+            E::Lte(Box::new(E::Int(-2)), Box::new(b())),
+            E::Magnitude(Box::new(E::GetX(Box::new(a())))),
+            E::SquareMagnitude(Box::new(a() - b())),
+            E::RotationInverse(Box::new(E::InputD)),
+            E::GetY(Box::new(E::Int(-2))),
+            E::GetZ(Box::new(-E::InputE)),
+            E::if_else(E::Gt(Box::new(a()), Box::new(b())), E::min(a(), b()), E::max(a(), E::Float(-0.5))),
+            E::Max3(Box::new(a()), Box::new(b()), Box::new(c())),
+            E::MinV(Box::new(a())),
+            E::Atan2(Box::new(a()), Box::new(b())),
+            E::Pow(Box::new(E::Sin(Box::new(a()))), Box::new(E::Int(2))),
+            E::Output(Box::new(E::Int(1))),
+            E::OutputV(Box::new(E::Int(2))) + E::previous_output_vector(0),
+            E::SetX { vector: Box::new(a()), x: Box::new(E::Int(1)) },
+            E::SetY { vector: Box::new(a()), y: Box::new(-b()) },
+            E::SetZ { vector: Box::new(a()), z: Box::new(E::Float(-3.5)) },
+            E::MakeRotationBetween { from_vector: Box::new(a()), to_vector: Box::new(b()) },
+            E::FromEuler { pitch: Box::new(a()), yaw: Box::new(E::Int(0)), roll: Box::new(-c()) },
+            E::FromEularV(Box::new(E::Vector(Box::new(a()), Box::new(E::Int(-1)), Box::new(E::Float(0.5))))),
+            E::ToEularV(Box::new(a())),
+            E::AngleBetween { from_vector: Box::new(a()), to_vector: Box::new(b()) },
+            E::Axis(Box::new(a())),
+        ];
+
+        for expr in exprs {
+            let string = expr.to_string();
+            assert_eq!(parse(&string), expr, "{string}");
+        }
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(parse("a + b * c"), a() + b() * c());
+        assert_eq!(parse("a * b + c"), a() * b() + c());
+        assert_eq!(parse("a - b - c"), a() - b() - c());
+        assert_eq!(parse("a / b % c"), a() / b() % c());
+        assert_eq!(parse("a x b * c"), E::Cross(Box::new(a()), Box::new(b())) * c());
+        assert_eq!(parse("a + b > c"), E::Gt(Box::new(a() + b()), Box::new(c())));
+        assert_eq!(
+            parse("a or b | c & a = b"),
+            E::FalseCoalesce(
+                Box::new(a()),
+                Box::new(E::OpOr(
+                    Box::new(b()),
+                    Box::new(E::OpAnd(Box::new(c()), Box::new(E::Eq(Box::new(a()), Box::new(b()))))),
+                )),
+            ),
+        );
+        assert_eq!(parse("(a + b) * c"), (a() + b()) * c());
+    }
+
+    #[test]
+    fn prefix_and_postfix() {
+        assert_eq!(parse("-a * b"), -a() * b());
+        assert_eq!(parse("!a & b"), E::OpAnd(Box::new(!a()), Box::new(b())));
+        assert_eq!(parse("-a.magnitude"), -E::Magnitude(Box::new(a())));
+        assert_eq!(parse("!a.x"), !E::GetX(Box::new(a())));
+        assert_eq!(parse("a.x.magnitude"), E::Magnitude(Box::new(E::GetX(Box::new(a())))));
+        assert_eq!(parse("Vector(a, b, c).y"), E::GetY(Box::new(E::Vector(Box::new(a()), Box::new(b()), Box::new(c())))));
+        assert_eq!(parse("- -a"), -(-a()));
+        // a minus directly before a number is a negative literal, not a negation
+        assert_eq!(parse("-2"), E::Int(-2));
+        assert_eq!(parse("-2.5"), E::Float(-2.5));
+        assert_eq!(parse("a - 2"), a() - E::Int(2));
+        assert_eq!(parse("a*-2"), a() * E::Int(-2));
+        // a dot after a number is only part of it when a digit follows
+        assert_eq!(parse("(2).x"), E::GetX(Box::new(E::Int(2))));
+        assert_eq!(parse("2.x"), E::GetX(Box::new(E::Int(2))));
+    }
+
+    #[test]
+    fn function_names_are_case_insensitive() {
+        assert_eq!(parse("SIN(a)"), E::Sin(Box::new(a())));
+        assert_eq!(parse("sety(a, 1)"), E::SetY { vector: Box::new(a()), y: Box::new(E::Int(1)) });
+        assert_eq!(parse("fromtorot(a, b)"), E::MakeRotationBetween { from_vector: Box::new(a()), to_vector: Box::new(b()) });
+        assert_eq!(parse("Output(0)"), E::previous_output(0));
+        assert_eq!(parse("OUTPUTV(1)"), E::previous_output_vector(1));
+    }
+
+    #[test]
+    fn expression_lists() {
+        assert_eq!(parse_expression_list(""), Ok(Vec::new()));
+        assert_eq!(parse_expression_list("a, If(b, 1, 2.0)"), Ok(vec![a(), E::if_else(b(), E::Int(1), E::Float(2.0))]));
+        assert_eq!(parse_expression_list("a, b c").unwrap_err().position(), 5);
+    }
+
+    #[test]
+    fn error_positions() {
+        for (string, position) in [
+            ("", 0),
+            ("a +", 3),
+            ("a + * b", 4),
+            ("Sin(a", 5),
+            ("(a + b", 6),
+            ("a $ b", 2),
+            ("a b", 2),
+            ("f + a", 0),
+            ("a + Foo(b)", 4),
+            ("Sin(a, b)", 0),
+            ("If(a, b)", 0),
+            ("(a).foo", 4),
+            ("(a).", 4),
+            ("(a).(b)", 4),
+            ("a, b", 1),
+            ("99999999999999999999", 0),
+        ] {
+            let error = E::parse(string).unwrap_err();
+            assert_eq!(error.position(), position, "`{string}`: {error}");
+        }
+    }
+}
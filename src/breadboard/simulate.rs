@@ -0,0 +1,872 @@
+//! Running a breadboard's logic without ftd, for testing
+//!
+//! A [`Simulation`] updates every component of a breadboard once per [`step`](Simulation::step), like ftd does each frame.
+//! Sensors don't simulate anything, their outputs are set with [`set_number`](Simulation::set_number) and the other set methods.
+//!
+//! ```
+//! use bakery::prelude::*;
+//!
+//! let breadboard = Breadboard::new();
+//! let altitude = breadboard.altitude(AltitudeOutputType::SeaLevel);
+//! let output = &altitude * 0.5;
+//!
+//! let mut simulation = Simulation::new(&breadboard);
+//! simulation.set_number(&altitude, 100.0);
+//! simulation.step().unwrap();
+//! assert_eq!(simulation.number(&output), Some(50.0));
+//! ```
+//!
+//! The simulation only approximates ftd:
+//!
+//! - Components are updated in the order they were added. An input wired to a later component reads
+//!   the output it had on the previous frame, and before that component has been updated it reads 0.
+//! - Values are f64, while ftd uses f32.
+//! - Math follows unity's conventions: angles are in degrees, `Round` rounds halves to even,
+//!   and `Sign(0)` is 1. What ftd does in edge cases such as division by 0 is not known.
+//! - Random inputs are treated as sensors, so their output must be set.
+
+use std::collections::HashMap;
+
+use super::{BNumber, BQuaternion, BVector3, Breadboard, Line, LineInner, LineValue, SimulationError};
+use super::evaluator::{finite_literal, EvaluatorExpression};
+
+/// Value of a line in a [`Simulation`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimValue {
+    Number(f64),
+    Vector([f64; 3]),
+    /// Quaternion in unity's order of x, y, z, w
+    Quaternion([f64; 4]),
+    String(String),
+}
+
+/// Runs a breadboard's components without ftd, see the [module docs](self)
+pub struct Simulation {
+    breadboard: Breadboard,
+    /// Outputs of each component from the last frame it was updated, sensor outputs which are not set are `None`
+    outputs: Vec<Vec<Option<SimValue>>>,
+    sensor_values: HashMap<LineInner, SimValue>,
+}
+
+impl Simulation {
+    /// Creates a simulation of `breadboard` which has not run any frames yet
+    ///
+    /// Components added to the breadboard later are also simulated.
+    pub fn new(breadboard: &Breadboard) -> Self {
+        Simulation {
+            breadboard: breadboard.clone(),
+            outputs: Vec::new(),
+            sensor_values: HashMap::new(),
+        }
+    }
+
+    /// Sets the output of a sensor, it is used for every following frame until it is set again
+    ///
+    /// # Panics
+    ///
+    /// Panics if the line is from a different breadboard or is not the output of a sensor.
+    pub fn set_number(&mut self, line: &Line<BNumber>, value: f64) {
+        self.set_sensor(line, SimValue::Number(value));
+    }
+
+    /// Sets the output of a vector sensor, see [`set_number`](Self::set_number)
+    pub fn set_vector(&mut self, line: &Line<BVector3>, value: [f64; 3]) {
+        self.set_sensor(line, SimValue::Vector(value));
+    }
+
+    /// Sets the output of a rotation sensor as a quaternion in unity's order of x, y, z, w, see [`set_number`](Self::set_number)
+    pub fn set_rotation(&mut self, line: &Line<BQuaternion>, value: [f64; 4]) {
+        self.set_sensor(line, SimValue::Quaternion(value));
+    }
+
+    fn set_sensor<T: LineValue + ?Sized>(&mut self, line: &Line<T>, value: SimValue) {
+        self.breadboard.verify_line(line);
+
+        let components = self.breadboard.0.components.borrow();
+        // sensors return None whatever their inputs are
+        assert!(
+            components[line.inner.component_index].simulate(&[], &[]).is_none(),
+            "line passed into simulation is not a sensor output",
+        );
+
+        self.sensor_values.insert(line.inner, value);
+    }
+
+    /// Value of `line` on the last frame, or `None` if it has not been computed or is not a number
+    pub fn number(&self, line: &Line<BNumber>) -> Option<f64> {
+        match self.value(line.inner)? {
+            SimValue::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Value of `line` on the last frame, see [`number`](Self::number)
+    pub fn vector(&self, line: &Line<BVector3>) -> Option<[f64; 3]> {
+        match self.value(line.inner)? {
+            SimValue::Vector(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Value of `line` on the last frame as a quaternion in unity's order of x, y, z, w, see [`number`](Self::number)
+    pub fn rotation(&self, line: &Line<BQuaternion>) -> Option<[f64; 4]> {
+        match self.value(line.inner)? {
+            SimValue::Quaternion(q) => Some(*q),
+            _ => None,
+        }
+    }
+
+    /// Value of any output on the last frame, or `None` if it has not been computed
+    pub fn value(&self, line: LineInner) -> Option<&SimValue> {
+        self.outputs.get(line.component_index)?
+            .get(line.output_index)?
+            .as_ref()
+    }
+
+    /// Updates every component once
+    ///
+    /// If this returns an error the frame is left partially updated.
+    pub fn step(&mut self) -> Result<(), SimulationError> {
+        let components = self.breadboard.0.components.borrow();
+        self.outputs.resize(components.len(), Vec::new());
+
+        for (component_index, component) in components.iter().enumerate() {
+            let inputs = component.inputs().iter()
+                .map(|line| self.read_input(component_index, *line))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let previous_outputs: Vec<SimValue> = self.outputs[component_index].iter()
+                .flatten()
+                .cloned()
+                .collect();
+
+            let outputs = match component.simulate(&inputs, &previous_outputs) {
+                Some(outputs) => outputs
+                    .map_err(|message| SimulationError::InvalidValues {
+                        component_index,
+                        message,
+                    })?
+                    .into_iter()
+                    .map(Some)
+                    .collect(),
+                None => (0..component.num_outputs())
+                    .map(|output_index| self.sensor_values.get(&LineInner {
+                        component_index,
+                        output_index,
+                    }).cloned())
+                    .collect(),
+            };
+
+            self.outputs[component_index] = outputs;
+        }
+
+        Ok(())
+    }
+
+    fn read_input(&self, component_index: usize, line: LineInner) -> Result<SimValue, SimulationError> {
+        let outputs = &self.outputs[line.component_index];
+        if outputs.is_empty() {
+            // the component is later in the list and has not been updated yet
+            return Ok(SimValue::Number(0.0));
+        }
+
+        outputs[line.output_index].clone()
+            .ok_or(SimulationError::MissingSensorValue {
+                component_index,
+                line,
+            })
+    }
+}
+
+impl EvaluatorExpression {
+    /// Computes the value of the expression, returning an error message if an operand has the wrong type
+    ///
+    /// `previous_outputs` is empty on the first frame, where every previous output reads as 0.
+    pub(super) fn evaluate(&self, inputs: &[SimValue], previous_outputs: &[SimValue]) -> Result<SimValue, String> {
+        let invalid = || format!("invalid operands for `{self}`");
+        let eval = |expr: &Self| expr.evaluate(inputs, previous_outputs);
+        let number = |expr: &Self| match eval(expr)? {
+            SimValue::Number(n) => Ok(n),
+            _ => Err(invalid()),
+        };
+        let vector = |expr: &Self| match eval(expr)? {
+            SimValue::Vector(v) => Ok(v),
+            _ => Err(invalid()),
+        };
+        let rotation = |expr: &Self| match eval(expr)? {
+            SimValue::Quaternion(q) => Ok(q),
+            _ => Err(invalid()),
+        };
+        let previous_output = |index: &Self, zero: SimValue| {
+            if previous_outputs.is_empty() {
+                return Ok(zero);
+            }
+
+            let index = number(index)?.round();
+            let output = if index >= 0.0 { previous_outputs.get(index as usize) } else { None };
+            match output {
+                Some(value) if std::mem::discriminant(value) == std::mem::discriminant(&zero) => Ok(value.clone()),
+                _ => Err(invalid()),
+            }
+        };
+
+        let num = |n: f64| Ok(SimValue::Number(n));
+        let bool_num = |b: bool| num(if b { 1.0 } else { 0.0 });
+        let vec = |v: [f64; 3]| Ok(SimValue::Vector(v));
+        let quat = |q: [f64; 4]| Ok(SimValue::Quaternion(q));
+
+        match self {
+            Self::InputA => inputs.first().cloned().ok_or_else(invalid),
+            Self::InputB => inputs.get(1).cloned().ok_or_else(invalid),
+            Self::InputC => inputs.get(2).cloned().ok_or_else(invalid),
+            Self::InputD => inputs.get(3).cloned().ok_or_else(invalid),
+            Self::InputE => inputs.get(4).cloned().ok_or_else(invalid),
+            Self::Int(n) => num(*n as f64),
+            Self::Float(n) => num(finite_literal(*n)),
+            Self::Sin(val) => num(number(val)?.to_radians().sin()),
+            Self::Cos(val) => num(number(val)?.to_radians().cos()),
+            Self::Tan(val) => num(number(val)?.to_radians().tan()),
+            Self::Sqrt(val) => num(number(val)?.sqrt()),
+            Self::Asin(val) => num(number(val)?.asin().to_degrees()),
+            Self::Acos(val) => num(number(val)?.acos().to_degrees()),
+            Self::Atan(val) => num(number(val)?.atan().to_degrees()),
+            // like unity's Mathf.Atan2 the first argument is y
+            Self::Atan2(val1, val2) => num(number(val1)?.atan2(number(val2)?).to_degrees()),
+            Self::Exp(val) => num(number(val)?.exp()),
+            Self::Log(val) => num(number(val)?.ln()),
+            Self::Pow(val1, val2) => num(number(val1)?.powf(number(val2)?)),
+            Self::Abs(val) => match eval(val)? {
+                SimValue::Number(n) => num(n.abs()),
+                SimValue::Vector(v) => vec(v.map(f64::abs)),
+                _ => Err(invalid()),
+            },
+            Self::Sign(val) => num(number(val)?.signum()),
+            Self::Round(val) => num(number(val)?.round_ties_even()),
+            Self::Floor(val) => num(number(val)?.floor()),
+            Self::Ceil(val) => num(number(val)?.ceil()),
+            Self::Max2(val1, val2) => num(number(val1)?.max(number(val2)?)),
+            Self::Max3(val1, val2, val3) => num(number(val1)?.max(number(val2)?).max(number(val3)?)),
+            Self::MaxV(val) => num(vector(val)?.into_iter().fold(f64::NEG_INFINITY, f64::max)),
+            Self::Min2(val1, val2) => num(number(val1)?.min(number(val2)?)),
+            Self::Min3(val1, val2, val3) => num(number(val1)?.min(number(val2)?).min(number(val3)?)),
+            Self::MinV(val) => num(vector(val)?.into_iter().fold(f64::INFINITY, f64::min)),
+            Self::If {
+                condition,
+                true_value,
+                false_value,
+            } => if number(condition)? != 0.0 {
+                eval(true_value)
+            } else {
+                eval(false_value)
+            },
+            Self::Vector(x, y, z) => vec([number(x)?, number(y)?, number(z)?]),
+            Self::MakeRotationBetween {
+                from_vector,
+                to_vector,
+            } => quat(rotation_between(vector(from_vector)?, vector(to_vector)?)),
+            Self::FromEuler {
+                pitch,
+                yaw,
+                roll,
+            } => quat(euler_to_rotation([number(pitch)?, number(yaw)?, number(roll)?])),
+            Self::FromEularV(val) => quat(euler_to_rotation(vector(val)?)),
+            Self::ToEularV(val) => vec(rotation_to_euler(rotation(val)?)),
+            Self::Angle(val) => num(2.0 * rotation(val)?[3].clamp(-1.0, 1.0).acos().to_degrees()),
+            Self::Axis(val) => {
+                let [x, y, z, w] = rotation(val)?;
+                let sin_half_angle = (1.0 - w * w).max(0.0).sqrt();
+                if sin_half_angle < EPSILON {
+                    // like unity, a rotation with no angle has the x axis
+                    vec([1.0, 0.0, 0.0])
+                } else {
+                    vec([x, y, z].map(|n| n / sin_half_angle))
+                }
+            },
+            Self::AngleBetween {
+                from_vector,
+                to_vector,
+            } => num(angle_between(vector(from_vector)?, vector(to_vector)?)),
+            Self::SetX {
+                vector: v,
+                x,
+            } => {
+                let mut v = vector(v)?;
+                v[0] = number(x)?;
+                vec(v)
+            },
+            Self::SetY {
+                vector: v,
+                y,
+            } => {
+                let mut v = vector(v)?;
+                v[1] = number(y)?;
+                vec(v)
+            },
+            Self::SetZ {
+                vector: v,
+                z,
+            } => {
+                let mut v = vector(v)?;
+                v[2] = number(z)?;
+                vec(v)
+            },
+            Self::Output(val) => previous_output(val, SimValue::Number(0.0)),
+            Self::OutputV(val) => previous_output(val, SimValue::Vector([0.0; 3])),
+            Self::GetX(val) => num(vector(val)?[0]),
+            Self::GetY(val) => num(vector(val)?[1]),
+            Self::GetZ(val) => num(vector(val)?[2]),
+            Self::Magnitude(val) => num(dot(vector(val)?, vector(val)?).sqrt()),
+            Self::SquareMagnitude(val) => num(dot(vector(val)?, vector(val)?)),
+            Self::RotationInverse(val) => quat(inverse(rotation(val)?)),
+            Self::Add(lhs, rhs) => match (eval(lhs)?, eval(rhs)?) {
+                (SimValue::Number(a), SimValue::Number(b)) => num(a + b),
+                (SimValue::Vector(a), SimValue::Vector(b)) => vec([a[0] + b[0], a[1] + b[1], a[2] + b[2]]),
+                (SimValue::String(a), SimValue::String(b)) => Ok(SimValue::String(a + &b)),
+                _ => Err(invalid()),
+            },
+            Self::Sub(lhs, rhs) => match (eval(lhs)?, eval(rhs)?) {
+                (SimValue::Number(a), SimValue::Number(b)) => num(a - b),
+                (SimValue::Vector(a), SimValue::Vector(b)) => vec([a[0] - b[0], a[1] - b[1], a[2] - b[2]]),
+                (SimValue::String(a), SimValue::String(b)) => Ok(SimValue::String(a.replace(&b, ""))),
+                _ => Err(invalid()),
+            },
+            Self::Cross(lhs, rhs) => vec(cross(vector(lhs)?, vector(rhs)?)),
+            Self::Mul(lhs, rhs) => match (eval(lhs)?, eval(rhs)?) {
+                (SimValue::Number(a), SimValue::Number(b)) => num(a * b),
+                (SimValue::Vector(a), SimValue::Vector(b)) => num(dot(a, b)),
+                (SimValue::Quaternion(q), SimValue::Vector(v)) | (SimValue::Vector(v), SimValue::Quaternion(q)) => vec(rotate(q, v)),
+                (SimValue::Number(n), SimValue::Vector(v)) | (SimValue::Vector(v), SimValue::Number(n)) => vec(v.map(|a| a * n)),
+                (SimValue::Quaternion(a), SimValue::Quaternion(b)) => quat(compose(a, b)),
+                _ => Err(invalid()),
+            },
+            Self::Div(lhs, rhs) => match (eval(lhs)?, eval(rhs)?) {
+                (SimValue::Number(a), SimValue::Number(b)) => num(a / b),
+                (SimValue::Vector(v), SimValue::Number(n)) => vec(v.map(|a| a / n)),
+                (SimValue::Vector(v), SimValue::Quaternion(q)) => vec(rotate(inverse(q), v)),
+                _ => Err(invalid()),
+            },
+            // like c#, the remainder has the sign of the dividend
+            Self::Mod(lhs, rhs) => num(number(lhs)? % number(rhs)?),
+            Self::Eq(lhs, rhs) => bool_num(number(lhs)? == number(rhs)?),
+            Self::Ne(lhs, rhs) => bool_num(number(lhs)? != number(rhs)?),
+            Self::Gt(lhs, rhs) => bool_num(number(lhs)? > number(rhs)?),
+            Self::Gte(lhs, rhs) => bool_num(number(lhs)? >= number(rhs)?),
+            Self::Lt(lhs, rhs) => bool_num(number(lhs)? < number(rhs)?),
+            Self::Lte(lhs, rhs) => bool_num(number(lhs)? <= number(rhs)?),
+            Self::Not(val) => bool_num(number(val)? == 0.0),
+            Self::OpAnd(lhs, rhs) => bool_num(number(lhs)? != 0.0 && number(rhs)? != 0.0),
+            Self::OpOr(lhs, rhs) => bool_num(number(lhs)? != 0.0 || number(rhs)? != 0.0),
+            Self::FalseCoalesce(lhs, rhs) => {
+                let lhs = number(lhs)?;
+                if lhs != 0.0 {
+                    num(lhs)
+                } else {
+                    num(number(rhs)?)
+                }
+            },
+            Self::Negate(val) => match eval(val)? {
+                SimValue::Number(n) => num(-n),
+                SimValue::Vector(v) => vec(v.map(|a| -a)),
+                _ => Err(invalid()),
+            },
+        }
+    }
+}
+
+/// Below this vectors and angles are treated as 0
+const EPSILON: f64 = 1e-9;
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(v: [f64; 3]) -> Option<[f64; 3]> {
+    let length = dot(v, v).sqrt();
+    (length > EPSILON).then(|| v.map(|a| a / length))
+}
+
+/// Unsigned angle between two vectors in degrees, 0 if either is zero like unity's `Vector3.Angle`
+fn angle_between(a: [f64; 3], b: [f64; 3]) -> f64 {
+    let lengths = (dot(a, a) * dot(b, b)).sqrt();
+    if lengths < EPSILON {
+        return 0.0;
+    }
+
+    (dot(a, b) / lengths).clamp(-1.0, 1.0).acos().to_degrees()
+}
+
+fn compose(a: [f64; 4], b: [f64; 4]) -> [f64; 4] {
+    let [ax, ay, az, aw] = a;
+    let [bx, by, bz, bw] = b;
+
+    [
+        aw * bx + ax * bw + ay * bz - az * by,
+        aw * by - ax * bz + ay * bw + az * bx,
+        aw * bz + ax * by - ay * bx + az * bw,
+        aw * bw - ax * bx - ay * by - az * bz,
+    ]
+}
+
+/// Inverse of a unit quaternion
+fn inverse(q: [f64; 4]) -> [f64; 4] {
+    [-q[0], -q[1], -q[2], q[3]]
+}
+
+fn rotate(q: [f64; 4], v: [f64; 3]) -> [f64; 3] {
+    let [x, y, z, _] = compose(compose(q, [v[0], v[1], v[2], 0.0]), inverse(q));
+    [x, y, z]
+}
+
+fn axis_rotation(axis: [f64; 3], angle: f64) -> [f64; 4] {
+    let (sin, cos) = (angle.to_radians() / 2.0).sin_cos();
+    [axis[0] * sin, axis[1] * sin, axis[2] * sin, cos]
+}
+
+/// Rotation from euler angles applied like unity, roll around z then pitch around x then yaw around y
+fn euler_to_rotation([pitch, yaw, roll]: [f64; 3]) -> [f64; 4] {
+    compose(
+        compose(axis_rotation([0.0, 1.0, 0.0], yaw), axis_rotation([1.0, 0.0, 0.0], pitch)),
+        axis_rotation([0.0, 0.0, 1.0], roll),
+    )
+}
+
+/// Euler angles of a rotation in the range `[0, 360)`, like unity's `Quaternion.eulerAngles`
+fn rotation_to_euler([x, y, z, w]: [f64; 4]) -> [f64; 3] {
+    let sin_pitch = (2.0 * (w * x - y * z)).clamp(-1.0, 1.0);
+    let pitch = sin_pitch.asin();
+
+    let (yaw, roll) = if sin_pitch.abs() < 1.0 - EPSILON {
+        (
+            (2.0 * (x * z + w * y)).atan2(1.0 - 2.0 * (x * x + y * y)),
+            (2.0 * (x * y + w * z)).atan2(1.0 - 2.0 * (x * x + z * z)),
+        )
+    } else {
+        // looking straight up or down, so yaw and roll rotate around the same axis and roll is left at 0
+        ((2.0 * (w * y - x * z)).atan2(1.0 - 2.0 * (y * y + z * z)), 0.0)
+    };
+
+    [pitch, yaw, roll].map(|angle| angle.to_degrees().rem_euclid(360.0))
+}
+
+/// Shortest rotation from one direction to another, like unity's `Quaternion.FromToRotation`
+fn rotation_between(from: [f64; 3], to: [f64; 3]) -> [f64; 4] {
+    let (Some(from), Some(to)) = (normalize(from), normalize(to)) else {
+        return [0.0, 0.0, 0.0, 1.0];
+    };
+
+    let cos_angle = dot(from, to);
+    if cos_angle < -1.0 + EPSILON {
+        // opposite directions, so any perpendicular axis works
+        let axis = normalize(cross([1.0, 0.0, 0.0], from))
+            .or_else(|| normalize(cross([0.0, 1.0, 0.0], from)))
+            .unwrap();
+        return axis_rotation(axis, 180.0);
+    }
+
+    let [x, y, z] = cross(from, to);
+    let w = 1.0 + cos_angle;
+    let length = (x * x + y * y + z * z + w * w).sqrt();
+
+    [x / length, y / length, z / length, w / length]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::breadboard::{AltitudeOutputType, BreadboardError, PidGains, StateTransition, MAX_MOVING_AVERAGE_WINDOW, SwitchOptions, TICK_DELTA_TIME};
+
+    fn assert_close(a: [f64; 3], b: [f64; 3]) {
+        for (a, b) in a.into_iter().zip(b) {
+            assert!((a - b).abs() < 1e-9, "{a} != {b}");
+        }
+    }
+
+    #[test]
+    fn counter_counts_rising_edges() {
+        let breadboard = Breadboard::new();
+        let increment = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let count = breadboard.counter(increment.clone(), None, 3.0);
+
+        let mut simulation = Simulation::new(&breadboard);
+        let mut counts = Vec::new();
+        for increment_value in [0.0, 1.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0] {
+            simulation.set_number(&increment, increment_value);
+            simulation.step().unwrap();
+            counts.push(simulation.number(&count).unwrap());
+        }
+
+        assert_eq!(counts, [0.0, 1.0, 1.0, 1.0, 2.0, 2.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn sr_latch_sets_holds_and_resets() {
+        let breadboard = Breadboard::new();
+        let set = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let reset = breadboard.altitude(AltitudeOutputType::WaveLevel);
+        let latch = breadboard.sr_latch(set.clone(), reset.clone());
+
+        let mut simulation = Simulation::new(&breadboard);
+        let mut outputs = Vec::new();
+        for (set_value, reset_value) in [(0.0, 0.0), (1.0, 0.0), (0.0, 0.0), (0.0, 1.0), (0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 0.0)] {
+            simulation.set_number(&set, set_value);
+            simulation.set_number(&reset, reset_value);
+            simulation.step().unwrap();
+            outputs.push(simulation.number(&latch).unwrap());
+        }
+
+        // starts reset, reset wins when both are set
+        assert_eq!(outputs, [0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn peak_and_trough_hold() {
+        let breadboard = Breadboard::new();
+        let signal = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let reset = breadboard.altitude(AltitudeOutputType::WaveLevel);
+        let peak = breadboard.peak_hold(signal.clone(), Some(reset.clone()));
+        let trough = breadboard.trough_hold(signal.clone(), None);
+
+        let mut simulation = Simulation::new(&breadboard);
+        let mut outputs = Vec::new();
+        for (signal_value, reset_value) in [(3.0, 0.0), (5.0, 0.0), (-2.0, 0.0), (1.0, 1.0), (0.0, 0.0)] {
+            simulation.set_number(&signal, signal_value);
+            simulation.set_number(&reset, reset_value);
+            simulation.step().unwrap();
+            outputs.push((simulation.number(&peak).unwrap(), simulation.number(&trough).unwrap()));
+        }
+
+        assert_eq!(outputs, [(3.0, 3.0), (5.0, 3.0), (5.0, -2.0), (1.0, -2.0), (1.0, -2.0)]);
+    }
+
+    #[test]
+    fn f32_operands_are_not_clamped() {
+        let breadboard = Breadboard::new();
+        let altitude = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let scaled = &altitude * 20000.0;
+        let offset = 50000.0 - &altitude;
+        let remainder = &altitude % 3.0;
+
+        let mut simulation = Simulation::new(&breadboard);
+        simulation.set_number(&altitude, 2.0);
+        simulation.step().unwrap();
+
+        assert_eq!(simulation.number(&scaled).unwrap(), 40000.0);
+        assert_eq!(simulation.number(&offset).unwrap(), 49998.0);
+        assert_eq!(simulation.number(&remainder).unwrap(), 2.0);
+        // each operation is a single evaluator, with no constant component for the f32
+        assert_eq!(breadboard.component_count(), 4);
+    }
+
+    #[test]
+    fn bounds_are_not_clamped() {
+        let breadboard = Breadboard::new();
+        let altitude = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let above = altitude.at_least(20000.0);
+        let below = altitude.at_most(-20000.0);
+        let within = altitude.at_least(-20000.0).at_most(20000.0);
+
+        let mut simulation = Simulation::new(&breadboard);
+        simulation.set_number(&altitude, 15000.0);
+        simulation.step().unwrap();
+
+        assert_eq!(simulation.number(&above).unwrap(), 20000.0);
+        assert_eq!(simulation.number(&below).unwrap(), -20000.0);
+        assert_eq!(simulation.number(&within).unwrap(), 15000.0);
+        // each bound is a single evaluator, with no constant component
+        assert_eq!(breadboard.component_count(), 5);
+    }
+
+    #[test]
+    fn state_machine_transitions() {
+        let breadboard = Breadboard::new();
+        let engage = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let retreat = breadboard.altitude(AltitudeOutputType::WaveLevel);
+        let state = breadboard.state_machine(0.0, &[
+            StateTransition { from: 0.0, to: 1.0, condition: engage.clone() },
+            StateTransition { from: 1.0, to: 2.0, condition: retreat.clone() },
+            StateTransition { from: 0.0, to: 2.0, condition: retreat.clone() },
+        ]);
+
+        let run = |frames: &[(f64, f64)]| {
+            let mut simulation = Simulation::new(&breadboard);
+            frames.iter()
+                .map(|(engage_value, retreat_value)| {
+                    simulation.set_number(&engage, *engage_value);
+                    simulation.set_number(&retreat, *retreat_value);
+                    simulation.step().unwrap();
+                    simulation.number(&state).unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // the first matching transition wins, and only one is taken per frame
+        assert_eq!(run(&[(0.0, 0.0), (1.0, 1.0), (1.0, 1.0), (0.0, 0.0)]), [0.0, 1.0, 2.0, 2.0]);
+        assert_eq!(run(&[(0.0, 1.0)]), [2.0]);
+    }
+
+    #[test]
+    fn moving_average_window() {
+        let breadboard = Breadboard::new();
+        let signal = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let average = breadboard.moving_average(signal.clone(), 3);
+
+        let mut simulation = Simulation::new(&breadboard);
+        let mut averages = Vec::new();
+        for signal_value in [3.0, 6.0, 9.0, 0.0, 0.0, 0.0] {
+            simulation.set_number(&signal, signal_value);
+            simulation.step().unwrap();
+            averages.push(simulation.number(&average).unwrap());
+        }
+
+        assert_eq!(averages, [1.0, 3.0, 6.0, 5.0, 3.0, 0.0]);
+        assert!(matches!(
+            breadboard.try_moving_average(signal, MAX_MOVING_AVERAGE_WINDOW + 1),
+            Err(BreadboardError::WindowTooLarge { window, max: MAX_MOVING_AVERAGE_WINDOW }) if window == MAX_MOVING_AVERAGE_WINDOW + 1,
+        ));
+    }
+
+    #[test]
+    fn evaluator_builder_feedback() {
+        let breadboard = Breadboard::new();
+        let step = breadboard.constant(1.0);
+        let mut builder = breadboard.evaluator_builder();
+        let step = builder.input(&step);
+        let count = builder.output::<BNumber>();
+        let doubled = builder.output::<BVector3>();
+
+        // doubled is set before count is, and reads count from the previous frame
+        builder.set_output(&doubled, EvaluatorExpression::Vector(
+            Box::new(count.previous() * EvaluatorExpression::Int(2)),
+            Box::new(EvaluatorExpression::Int(0)),
+            Box::new(EvaluatorExpression::Int(0)),
+        ));
+        builder.set_output(&count, count.previous() + step);
+
+        let (count, doubled) = builder.finish().lines((count, doubled));
+        let mut simulation = Simulation::new(&breadboard);
+        let mut outputs = Vec::new();
+        for _ in 0..3 {
+            simulation.step().unwrap();
+            outputs.push((simulation.number(&count).unwrap(), simulation.vector(&doubled).unwrap()[0]));
+        }
+
+        assert_eq!(outputs, [(1.0, 0.0), (2.0, 2.0), (3.0, 4.0)]);
+    }
+
+    #[test]
+    fn soft_start_ramps_and_resets() {
+        let breadboard = Breadboard::new();
+        let enable = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let target = breadboard.constant(8.0);
+        let output = breadboard.soft_start(target, Some(enable.clone()), 0.1);
+
+        let mut simulation = Simulation::new(&breadboard);
+        let mut outputs = Vec::new();
+        for enable_value in [1.0, 1.0, 1.0, 1.0, 1.0, 0.0, 1.0] {
+            simulation.set_number(&enable, enable_value);
+            simulation.step().unwrap();
+            outputs.push(simulation.number(&output).unwrap());
+        }
+
+        for (output, expected) in outputs.into_iter().zip([2.0, 4.0, 6.0, 8.0, 8.0, 0.0, 2.0]) {
+            assert!((output - expected).abs() < 1e-6, "{output} != {expected}");
+        }
+    }
+
+    #[test]
+    fn delay_by_one_frame() {
+        let breadboard = Breadboard::new();
+        let altitude = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let position = breadboard.position();
+        let delayed = breadboard.delay(altitude.clone());
+        let delayed_vector = breadboard.delay_vector(position.clone());
+
+        let mut simulation = Simulation::new(&breadboard);
+        let mut outputs = Vec::new();
+        for value in [3.0, -1.0, 8.0] {
+            simulation.set_number(&altitude, value);
+            simulation.set_vector(&position, [value, 0.0, 1.0]);
+            simulation.step().unwrap();
+            outputs.push((simulation.number(&delayed).unwrap(), simulation.vector(&delayed_vector).unwrap()));
+        }
+
+        assert_eq!(outputs, [
+            (0.0, [0.0, 0.0, 0.0]),
+            (3.0, [3.0, 0.0, 1.0]),
+            (-1.0, [-1.0, 0.0, 1.0]),
+        ]);
+    }
+
+    #[test]
+    fn derivative_per_second() {
+        let breadboard = Breadboard::new();
+        let altitude = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let position = breadboard.position();
+        let rate = breadboard.derivative(altitude.clone());
+        let rate_vector = breadboard.derivative_vector(position.clone());
+
+        let mut simulation = Simulation::new(&breadboard);
+        let mut outputs = Vec::new();
+        for value in [3.0, 5.0, 4.0] {
+            simulation.set_number(&altitude, value);
+            simulation.set_vector(&position, [value, 0.0, 1.0]);
+            simulation.step().unwrap();
+            outputs.push((simulation.number(&rate).unwrap(), simulation.vector(&rate_vector).unwrap()));
+        }
+
+        // 0 on the first frame, then the change divided by the frame time
+        for ((rate, rate_vector), expected) in outputs.into_iter().zip([0.0, 2.0 / TICK_DELTA_TIME, -1.0 / TICK_DELTA_TIME]) {
+            assert!((rate - expected).abs() < 1e-6, "{rate} != {expected}");
+            assert_close(rate_vector, [expected, 0.0, 0.0]);
+        }
+    }
+
+    #[test]
+    fn pid_integral_accumulates() {
+        let breadboard = Breadboard::new();
+        let process = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let setpoint = breadboard.constant(10.0);
+        let integral = breadboard.pid(setpoint.clone(), process.clone(), PidGains::new(0.0, 2.0, 0.0));
+        let derivative = breadboard.pid(setpoint, process.clone(), PidGains::new(0.0, 0.0, 1.0));
+
+        let mut simulation = Simulation::new(&breadboard);
+        let mut outputs = Vec::new();
+        for process_value in [6.0, 6.0, 6.0, 8.0] {
+            simulation.set_number(&process, process_value);
+            simulation.step().unwrap();
+            outputs.push((simulation.number(&integral).unwrap(), simulation.number(&derivative).unwrap()));
+        }
+
+        let integral_step = 2.0 * 4.0 * TICK_DELTA_TIME;
+        let expected = [
+            (integral_step, 0.0),
+            (2.0 * integral_step, 0.0),
+            (3.0 * integral_step, 0.0),
+            (3.0 * integral_step + 2.0 * 2.0 * TICK_DELTA_TIME, -2.0 / TICK_DELTA_TIME),
+        ];
+        for ((integral, derivative), (expected_integral, expected_derivative)) in outputs.into_iter().zip(expected) {
+            assert!((integral - expected_integral).abs() < 1e-6, "{integral} != {expected_integral}");
+            assert!((derivative - expected_derivative).abs() < 1e-4, "{derivative} != {expected_derivative}");
+        }
+    }
+
+    #[test]
+    fn switch_open_value_semantics() {
+        let breadboard = Breadboard::new();
+        let signal = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let passthrough = breadboard.constant(7.0);
+        let output = breadboard.switch(passthrough, signal.clone(), SwitchOptions::new(2.0, -3.0));
+
+        let mut simulation = Simulation::new(&breadboard);
+        let mut outputs = Vec::new();
+        for signal_value in [1.0, 2.0, 2.5] {
+            simulation.set_number(&signal, signal_value);
+            simulation.step().unwrap();
+            outputs.push(simulation.number(&output).unwrap());
+        }
+
+        // open value at or below the threshold, passthrough only strictly above it
+        assert_eq!(outputs, [-3.0, -3.0, 7.0]);
+    }
+
+    #[test]
+    fn multi_switch_boundaries() {
+        let breadboard = Breadboard::new();
+        let passthrough = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let signal = breadboard.altitude(AltitudeOutputType::WaveLevel);
+        let switched = breadboard.multi_switch(passthrough.clone(), signal.clone(), 1.0, 5.0, -1.0, 9.0);
+        let inverted = breadboard.multi_switch(passthrough.clone(), signal.clone(), 5.0, 1.0, -1.0, 9.0);
+
+        let mut simulation = Simulation::new(&breadboard);
+        let mut outputs = Vec::new();
+        for signal_value in [0.5, 1.0, 3.0, 5.0, 5.5] {
+            simulation.set_number(&passthrough, 100.0);
+            simulation.set_number(&signal, signal_value);
+            simulation.step().unwrap();
+            outputs.push((simulation.number(&switched).unwrap(), simulation.number(&inverted).unwrap()));
+        }
+
+        // exactly low is low and exactly high is passthrough, with inverted thresholds the low check wins
+        assert_eq!(outputs, [(-1.0, -1.0), (-1.0, -1.0), (100.0, -1.0), (100.0, -1.0), (9.0, 9.0)]);
+    }
+
+    #[test]
+    fn lookup_rounds_and_clamps_index() {
+        let breadboard = Breadboard::new();
+        let index = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let entry = breadboard.lookup(index.clone(), &[10.0, 20.0, 30.0]);
+        let empty = breadboard.lookup(index.clone(), &[]);
+
+        let mut simulation = Simulation::new(&breadboard);
+        let mut outputs = Vec::new();
+        for index_value in [-1.0, 0.49, 0.5, 1.5, 2.0, 3.0] {
+            simulation.set_number(&index, index_value);
+            simulation.step().unwrap();
+            outputs.push((simulation.number(&entry).unwrap(), simulation.number(&empty).unwrap()));
+        }
+
+        assert_eq!(outputs, [(10.0, 0.0), (10.0, 0.0), (20.0, 0.0), (30.0, 0.0), (30.0, 0.0), (30.0, 0.0)]);
+    }
+
+    #[test]
+    fn comparison_as_switch_signal() {
+        let breadboard = Breadboard::new();
+        let altitude = breadboard.altitude(AltitudeOutputType::SeaLevel);
+        let above = breadboard.gt(altitude.clone(), breadboard.constant(100.0));
+        let passthrough = breadboard.constant(7.0);
+        let output = breadboard.switch(passthrough.clone(), above.clone(), SwitchOptions::default());
+        // true is 1, which is not above a threshold of 1
+        let never_closed = breadboard.switch(passthrough, above, SwitchOptions::default().with_threshold(1.0));
+
+        let mut simulation = Simulation::new(&breadboard);
+        let mut outputs = Vec::new();
+        for altitude_value in [50.0, 100.5] {
+            simulation.set_number(&altitude, altitude_value);
+            simulation.step().unwrap();
+            outputs.push((simulation.number(&output).unwrap(), simulation.number(&never_closed).unwrap()));
+        }
+
+        assert_eq!(outputs, [(0.0, 0.0), (7.0, 0.0)]);
+    }
+
+    #[test]
+    fn missing_sensor_value() {
+        let breadboard = Breadboard::new();
+        let position = breadboard.position();
+        let _ = position.magnitude();
+
+        let error = Simulation::new(&breadboard).step().unwrap_err();
+        assert_eq!(error, SimulationError::MissingSensorValue {
+            component_index: 1,
+            line: position.inner,
+        });
+    }
+
+    #[test]
+    fn euler_round_trip() {
+        let angles = [30.0, 200.0, 10.0];
+        assert_close(rotation_to_euler(euler_to_rotation(angles)), angles);
+    }
+
+    #[test]
+    fn positive_yaw_turns_forward_right() {
+        let forward = rotate(euler_to_rotation([0.0, 90.0, 0.0]), [0.0, 0.0, 1.0]);
+        assert_close(forward, [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn rotation_between_rotates_from_to_to() {
+        let from = [1.0, 2.0, 3.0];
+        let to = normalize([-2.0, 0.5, 1.0]).unwrap();
+        assert_close(rotate(rotation_between(from, to), normalize(from).unwrap()), to);
+        assert_close(rotate(rotation_between(to, to.map(|a| -a)), to), to.map(|a| -a));
+    }
+}
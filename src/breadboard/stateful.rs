@@ -0,0 +1,600 @@
+//! Components which keep state between frames
+//!
+//! These are built from evaluators which read back their own outputs from the previous frame,
+//! so they do not need any extra memory components. On the first frame every previous output reads as 0.
+
+use std::marker::PhantomData;
+
+use super::{BNumber, BVector3, Breadboard, BreadboardError, Line, LineValue};
+use super::evaluator::{Evaluator, EvaluatorExpression};
+
+/// Time in seconds between breadboard updates
+///
+/// Ftd updates breadboards at its fixed physics rate of 40 times per second.
+/// Every time based helper in this crate assumes this interval.
+pub const TICK_DELTA_TIME: f64 = 1.0 / 40.0;
+
+/// Longest window supported by [`Breadboard::moving_average`]
+///
+/// Every sample in the window is an evaluator output and a term of the average expression, so long windows make a very long expression.
+pub const MAX_MOVING_AVERAGE_WINDOW: usize = 64;
+
+/// A transition of a state machine built by [`Breadboard::state_machine`]
+#[derive(Clone)]
+pub struct StateTransition {
+    /// State this transition leaves from
+    pub from: f32,
+    /// State this transition goes to
+    pub to: f32,
+    /// The transition is taken on a frame where this is nonzero while in the `from` state
+    pub condition: Line<BNumber>,
+}
+
+/// Gains of a controller built by [`Breadboard::pid`] or [`Breadboard::pid_vector`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PidGains {
+    /// Gain of the error
+    pub kp: f32,
+    /// Gain of the error integrated over time in seconds
+    pub ki: f32,
+    /// Gain of the rate of change of the error per second
+    pub kd: f32,
+}
+
+impl PidGains {
+    pub fn new(kp: f32, ki: f32, kd: f32) -> Self {
+        PidGains {
+            kp,
+            ki,
+            kd,
+        }
+    }
+}
+
+/// Line value types an evaluator can read back from its own outputs
+///
+/// Ftd's evaluator can only read back numbers and vectors.
+pub trait FeedbackValue: LineValue {
+    /// Expression reading output `index` of the evaluator from the previous frame
+    fn previous_output(index: usize) -> EvaluatorExpression;
+
+    /// Expression for zero of this type, which is what feedback reads on the first frame
+    fn zero() -> EvaluatorExpression;
+}
+
+impl FeedbackValue for BNumber {
+    fn previous_output(index: usize) -> EvaluatorExpression {
+        EvaluatorExpression::previous_output(index as i64)
+    }
+
+    fn zero() -> EvaluatorExpression {
+        EvaluatorExpression::Int(0)
+    }
+}
+
+impl FeedbackValue for BVector3 {
+    fn previous_output(index: usize) -> EvaluatorExpression {
+        EvaluatorExpression::previous_output_vector(index as i64)
+    }
+
+    fn zero() -> EvaluatorExpression {
+        EvaluatorExpression::Vector(
+            Box::new(EvaluatorExpression::Int(0)),
+            Box::new(EvaluatorExpression::Int(0)),
+            Box::new(EvaluatorExpression::Int(0)),
+        )
+    }
+}
+
+/// Builds a single evaluator with several outputs, which can read its own outputs from the previous frame
+///
+/// Outputs are declared with [`output`](Self::output) before their expressions are set, so an expression
+/// can read back any output of the evaluator, including its own. Outputs are numbered in the order they are declared,
+/// which is the index the evaluator saves each expression at and the index lines to it are wired from,
+/// so the order expressions are set in does not matter. Outputs can have different types, and all of them share
+/// the evaluator's inputs, so several values derived from the same lines only need one component.
+/// [`BuiltEvaluator::lines`] returns the lines of several outputs at once as a tuple.
+/// An output read back with [`EvaluatorOutput::previous`] has the value it had on the previous frame,
+/// so feedback is always delayed by one frame, and reads as 0 (or the zero vector) on the first frame.
+///
+/// A free running oscillator, which integrates its own outputs:
+///
+/// ```
+/// use bakery::prelude::*;
+///
+/// let breadboard = Breadboard::new();
+/// let mut builder = breadboard.evaluator_builder();
+/// let position = builder.output::<BNumber>();
+/// let velocity = builder.output::<BNumber>();
+/// let started = builder.output::<BNumber>();
+///
+/// let dt = EvaluatorExpression::Float(TICK_DELTA_TIME);
+/// // starts at 1, since every output reads as 0 on the first frame
+/// builder.set_output(&position, EvaluatorExpression::If {
+///     condition: Box::new(started.previous()),
+///     true_value: Box::new(position.previous() + velocity.previous() * dt.clone()),
+///     false_value: Box::new(EvaluatorExpression::Int(1)),
+/// });
+/// builder.set_output(&velocity, velocity.previous() - position.previous() * dt);
+/// builder.set_output(&started, EvaluatorExpression::Int(1));
+///
+/// let evaluator = builder.finish();
+/// let oscillator = evaluator.line(&position);
+/// ```
+pub struct EvaluatorBuilder {
+    breadboard: Breadboard,
+    eval: Evaluator,
+    /// Expression of each declared output, `None` until it is set
+    outputs: Vec<Option<EvaluatorExpression>>,
+}
+
+impl EvaluatorBuilder {
+    /// Adds `line` as an input of the evaluator, and returns the expression reading it
+    ///
+    /// Passing the same line more than once reuses its input.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `line` is from a different breadboard, or the evaluator already has 5 other inputs.
+    pub fn input<T: LineValue + ?Sized>(&mut self, line: &Line<T>) -> EvaluatorExpression {
+        let [input] = self.breadboard.evaluator_inputs(&mut self.eval, [line]);
+        input
+    }
+
+    /// Declares the next output of the evaluator, its expression must be set with [`set_output`](Self::set_output)
+    pub fn output<T: LineValue>(&mut self) -> EvaluatorOutput<T> {
+        self.outputs.push(None);
+
+        EvaluatorOutput {
+            index: self.outputs.len() - 1,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Sets the expression of `output`, replacing any expression set before
+    ///
+    /// The expression is not checked against `T`, only that its operands have valid types when debug assertions are on.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `output` was not declared by this builder.
+    pub fn set_output<T: LineValue>(&mut self, output: &EvaluatorOutput<T>, expr: EvaluatorExpression) {
+        let slot = self.outputs.get_mut(output.index)
+            .expect("output was not declared by this evaluator builder");
+        *slot = Some(expr);
+    }
+
+    /// Inserts the evaluator into the breadboard
+    ///
+    /// # Panics
+    ///
+    /// Panics if any declared output has no expression set.
+    pub fn finish(mut self) -> BuiltEvaluator {
+        for (i, expr) in self.outputs.into_iter().enumerate() {
+            let expr = expr.unwrap_or_else(|| panic!("evaluator output {i} was declared but never set"));
+            self.eval.add_output(expr);
+        }
+
+        let component_index = self.breadboard.insert_component(self.eval);
+
+        BuiltEvaluator {
+            breadboard: self.breadboard,
+            component_index,
+        }
+    }
+}
+
+/// Output of an evaluator being built by an [`EvaluatorBuilder`], which carries `T`
+///
+/// Tuples of up to 5 outputs, which can have different types, can be turned into lines with [`BuiltEvaluator::lines`].
+pub struct EvaluatorOutput<T: LineValue> {
+    index: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: LineValue> EvaluatorOutput<T> {
+    /// Index of this output in the evaluator
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl<T: FeedbackValue> EvaluatorOutput<T> {
+    /// Expression reading the value this output had on the previous frame, which is 0 on the first frame
+    pub fn previous(&self) -> EvaluatorExpression {
+        T::previous_output(self.index)
+    }
+}
+
+impl<T: LineValue> Clone for EvaluatorOutput<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: LineValue> Copy for EvaluatorOutput<T> {}
+
+/// Evaluator inserted by [`EvaluatorBuilder::finish`]
+pub struct BuiltEvaluator {
+    breadboard: Breadboard,
+    component_index: usize,
+}
+
+impl BuiltEvaluator {
+    /// Line carrying `output` of this evaluator
+    ///
+    /// `output` must be from the builder which built this evaluator, otherwise the line is for the wrong output.
+    pub fn line<T: LineValue>(&self, output: &EvaluatorOutput<T>) -> Line<T> {
+        Line::new(self.breadboard.clone(), self.component_index, output.index)
+    }
+
+    /// Lines carrying each output of `outputs`, in the same order, see [`line`](Self::line)
+    pub fn lines<O: EvaluatorOutputGroup>(&self, outputs: O) -> O::Lines {
+        outputs.lines(self)
+    }
+}
+
+/// Tuple of [`EvaluatorOutput`]s, which [`BuiltEvaluator::lines`] turns into a tuple of lines with the same types
+pub trait EvaluatorOutputGroup {
+    type Lines;
+
+    fn lines(&self, evaluator: &BuiltEvaluator) -> Self::Lines;
+}
+
+macro_rules! impl_evaluator_output_group {
+    ($($output:ident: $typ:ident),+) => {
+        impl<$($typ: LineValue),+> EvaluatorOutputGroup for ($(EvaluatorOutput<$typ>,)+) {
+            type Lines = ($(Line<$typ>,)+);
+
+            fn lines(&self, evaluator: &BuiltEvaluator) -> Self::Lines {
+                let ($($output,)+) = self;
+                ($(evaluator.line($output),)+)
+            }
+        }
+    };
+}
+
+impl_evaluator_output_group!(a: A);
+impl_evaluator_output_group!(a: A, b: B);
+impl_evaluator_output_group!(a: A, b: B, c: C);
+impl_evaluator_output_group!(a: A, b: B, c: C, d: D);
+impl_evaluator_output_group!(a: A, b: B, c: C, d: D, e: E);
+
+impl Breadboard {
+    /// Starts building an evaluator with several outputs which can read back its own outputs, see [`EvaluatorBuilder`]
+    pub fn evaluator_builder(&self) -> EvaluatorBuilder {
+        EvaluatorBuilder {
+            breadboard: self.clone(),
+            eval: Evaluator::default(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Time in seconds between breadboard updates
+    ///
+    /// No ftd sensor for the frame time is known, so this is a constant [`TICK_DELTA_TIME`].
+    pub fn delta_time(&self) -> Line<BNumber> {
+        self.precise_constant(TICK_DELTA_TIME)
+    }
+
+    /// Set / reset latch
+    ///
+    /// A nonzero `set` drives the output to 1 and a nonzero `reset` drives it to 0,
+    /// otherwise the previous output is held. Reset wins if both are nonzero on the same frame.
+    /// The latch starts out reset.
+    pub fn sr_latch(&self, set: Line<BNumber>, reset: Line<BNumber>) -> Line<BNumber> {
+        self.evaluator_expr2(set, reset, |set, reset| EvaluatorExpression::If {
+            condition: reset,
+            true_value: Box::new(EvaluatorExpression::Int(0)),
+            false_value: Box::new(EvaluatorExpression::If {
+                condition: set,
+                true_value: Box::new(EvaluatorExpression::Int(1)),
+                false_value: Box::new(EvaluatorExpression::previous_output(0)),
+            }),
+        })
+    }
+
+    /// Outputs the largest value `signal` has had since the first frame or the last frame `reset` was nonzero
+    ///
+    /// The held value starts at the first sample, and on a frame where `reset` is nonzero it restarts from the current sample.
+    /// If `reset` is `None` it is [`b_false`](Self::b_false), so the value is never reset.
+    pub fn peak_hold(&self, signal: Line<BNumber>, reset: Option<Line<BNumber>>) -> Line<BNumber> {
+        self.running_extreme(signal, reset, EvaluatorExpression::max)
+    }
+
+    /// Outputs the smallest value `signal` has had since the first frame or the last frame `reset` was nonzero
+    ///
+    /// The held value starts at the first sample, and on a frame where `reset` is nonzero it restarts from the current sample.
+    /// If `reset` is `None` it is [`b_false`](Self::b_false), so the value is never reset.
+    pub fn trough_hold(&self, signal: Line<BNumber>, reset: Option<Line<BNumber>>) -> Line<BNumber> {
+        self.running_extreme(signal, reset, EvaluatorExpression::min)
+    }
+
+    fn running_extreme(
+        &self,
+        signal: Line<BNumber>,
+        reset: Option<Line<BNumber>>,
+        extreme_fn: fn(EvaluatorExpression, EvaluatorExpression) -> EvaluatorExpression,
+    ) -> Line<BNumber> {
+        let reset = reset.unwrap_or_else(|| self.b_false());
+
+        let mut eval = Evaluator::default();
+        let [signal, reset] = self.evaluator_inputs(&mut eval, [&signal, &reset]);
+
+        // output 1 is 0 only on the first frame, so the first sample is used instead of the initial 0
+        let restart = EvaluatorExpression::OpOr(
+            Box::new(reset),
+            Box::new(!EvaluatorExpression::previous_output(1)),
+        );
+
+        eval.add_output(EvaluatorExpression::if_else(
+            restart,
+            signal.clone(),
+            extreme_fn(signal, EvaluatorExpression::previous_output(0)),
+        ));
+        eval.add_output(EvaluatorExpression::Int(1));
+
+        self.insert_component_with_output(eval)
+    }
+
+    /// Counter which goes up by 1 on every rising edge of `increment`, and wraps back to 0 when it reaches `modulo`
+    ///
+    /// A rising edge is a frame where `increment` is nonzero after being 0 on the previous frame,
+    /// so holding `increment` high counts once. The count starts at 0, and since `increment` counts as 0
+    /// before the first frame, an `increment` which is already nonzero on the first frame is counted.
+    /// On a frame where `reset` is nonzero the count goes back to 0 and no edge is counted.
+    /// If `reset` is `None` it is [`b_false`](Self::b_false), so the count is never reset.
+    /// If `modulo` is not positive the count never wraps.
+    pub fn counter(&self, increment: Line<BNumber>, reset: Option<Line<BNumber>>, modulo: f32) -> Line<BNumber> {
+        let reset = reset.unwrap_or_else(|| self.b_false());
+
+        let mut eval = Evaluator::default();
+        let [increment, reset] = self.evaluator_inputs(&mut eval, [&increment, &reset]);
+
+        // output 1 is increment from the previous frame
+        let rising_edge = EvaluatorExpression::OpAnd(
+            Box::new(increment.clone()),
+            Box::new(!EvaluatorExpression::previous_output(1)),
+        );
+
+        let next_count = EvaluatorExpression::previous_output(0) + EvaluatorExpression::Int(1);
+        // the count is never negative, so % already wraps it the same way as a euclidean modulo
+        let next_count = if modulo > 0.0 {
+            next_count % EvaluatorExpression::Float(modulo.into())
+        } else {
+            next_count
+        };
+
+        eval.add_output(EvaluatorExpression::if_else(
+            reset,
+            EvaluatorExpression::Int(0),
+            EvaluatorExpression::if_else(rising_edge, next_count, EvaluatorExpression::previous_output(0)),
+        ));
+        eval.add_output(increment);
+
+        self.insert_component_with_output(eval)
+    }
+
+    /// Average of the last `window` samples of `signal`, including the current one
+    ///
+    /// The samples are kept as extra outputs of a single evaluator which shift along by one every frame, so this only adds
+    /// one component, but the evaluator has `window` outputs and an expression term for each sample.
+    /// The average lags `signal` by `(window - 1) / 2` frames, and samples from before the first frame read as 0,
+    /// so the output ramps up over the first `window` frames. A `window` of 0 is treated as 1, which outputs `signal` unchanged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is larger than [`MAX_MOVING_AVERAGE_WINDOW`].
+    pub fn moving_average(&self, signal: Line<BNumber>, window: usize) -> Line<BNumber> {
+        self.try_moving_average(signal, window).unwrap()
+    }
+
+    /// Like [`moving_average`](Self::moving_average), but returns an error instead of panicking if `window` is too large
+    pub fn try_moving_average(&self, signal: Line<BNumber>, window: usize) -> Result<Line<BNumber>, BreadboardError> {
+        if window > MAX_MOVING_AVERAGE_WINDOW {
+            return Err(BreadboardError::WindowTooLarge {
+                window,
+                max: MAX_MOVING_AVERAGE_WINDOW,
+            });
+        }
+
+        self.try_verify_insertable()?;
+
+        let window = window.max(1);
+        let mut eval = Evaluator::default();
+        let [signal] = self.evaluator_inputs(&mut eval, [&signal]);
+
+        // output 1 holds the sample from 1 frame ago, output 2 from 2 frames ago, and so on
+        let sum = (1..window)
+            .map(|i| EvaluatorExpression::previous_output(i as i64))
+            .fold(signal.clone(), |sum, sample| sum + sample);
+
+        eval.add_output(sum / EvaluatorExpression::Int(window as i64));
+        if window > 1 {
+            eval.add_output(signal);
+        }
+        for i in 2..window {
+            eval.add_output(EvaluatorExpression::previous_output(i as i64 - 1));
+        }
+
+        Ok(self.insert_component_with_output(eval))
+    }
+
+    /// Ramps up to `target` over `ramp_seconds` after `enable` becomes nonzero, and outputs 0 while `enable` is 0
+    ///
+    /// The output is `target` scaled by a ramp fraction, which follows `enable` by rising from 0 to 1 at a fixed rate
+    /// so it takes `ramp_seconds` to reach 1. Changes of `target` are followed immediately, only the start is ramped.
+    /// On a frame where `enable` is 0 the fraction resets to 0, so every time the system is enabled it ramps up again.
+    /// The fraction starts at 0, so if `enable` is nonzero on the first frame the output is already one frame into the ramp.
+    /// If `ramp_seconds` is not positive there is no ramp and `target` is output as soon as `enable` is nonzero.
+    /// If `enable` is `None` it is [`b_true`](Self::b_true), so the output ramps up once from the first frame.
+    pub fn soft_start(&self, target: Line<BNumber>, enable: Option<Line<BNumber>>, ramp_seconds: f32) -> Line<BNumber> {
+        let enable = enable.unwrap_or_else(|| self.b_true());
+
+        let mut eval = Evaluator::default();
+        let [target, enable] = self.evaluator_inputs(&mut eval, [&target, &enable]);
+
+        let step = if ramp_seconds > 0.0 {
+            TICK_DELTA_TIME / f64::from(ramp_seconds)
+        } else {
+            1.0
+        };
+
+        // output 1 holds the ramp fraction
+        let fraction = EvaluatorExpression::if_else(
+            enable,
+            EvaluatorExpression::min(
+                EvaluatorExpression::previous_output(1) + EvaluatorExpression::Float(step),
+                EvaluatorExpression::Int(1),
+            ),
+            EvaluatorExpression::Int(0),
+        );
+
+        eval.add_output(target * fraction.clone());
+        eval.add_output(fraction);
+
+        self.insert_component_with_output(eval)
+    }
+
+    /// Outputs the current state of a state machine, with states identified by numbers
+    ///
+    /// The machine starts in `initial_state`. Every frame at most one transition is taken,
+    /// if several transitions from the current state have a nonzero condition the first one in `transitions` wins.
+    /// States are compared exactly, so they should be whole numbers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transitions use more than 5 distinct condition lines, since they must all be inputs of one evaluator.
+    pub fn state_machine(&self, initial_state: f32, transitions: &[StateTransition]) -> Line<BNumber> {
+        let mut eval = Evaluator::default();
+
+        // output 1 is 0 only on the first frame
+        let current_state = EvaluatorExpression::if_else(
+            EvaluatorExpression::previous_output(1),
+            EvaluatorExpression::previous_output(0),
+            EvaluatorExpression::Float(initial_state.into()),
+        );
+
+        let conditions: Vec<_> = transitions.iter()
+            .map(|transition| {
+                let [condition] = self.evaluator_inputs(&mut eval, [&transition.condition]);
+                condition
+            })
+            .collect();
+
+        // build from the lowest priority transition outwards so the first transition is checked first
+        let mut next_state = current_state.clone();
+        for (transition, condition) in transitions.iter().zip(conditions).rev() {
+            let in_from_state = EvaluatorExpression::Eq(
+                Box::new(current_state.clone()),
+                Box::new(EvaluatorExpression::Float(transition.from.into())),
+            );
+
+            next_state = EvaluatorExpression::if_else(
+                EvaluatorExpression::OpAnd(Box::new(in_from_state), Box::new(condition)),
+                EvaluatorExpression::Float(transition.to.into()),
+                next_state,
+            );
+        }
+
+        eval.add_output(next_state);
+        eval.add_output(EvaluatorExpression::Int(1));
+
+        self.insert_component_with_output(eval)
+    }
+
+    /// Outputs the value `signal` had on the previous frame
+    ///
+    /// The output is 0 on the first frame, since there is no previous value yet.
+    pub fn delay(&self, signal: Line<BNumber>) -> Line<BNumber> {
+        self.delay_of(signal)
+    }
+
+    /// Outputs the value `signal` had on the previous frame, see [`delay`](Self::delay)
+    ///
+    /// The output is the zero vector on the first frame.
+    pub fn delay_vector(&self, signal: Line<BVector3>) -> Line<BVector3> {
+        self.delay_of(signal)
+    }
+
+    fn delay_of<T: FeedbackValue>(&self, signal: Line<T>) -> Line<T> {
+        let mut eval = Evaluator::default();
+        let [signal] = self.evaluator_inputs(&mut eval, [&signal]);
+
+        // output 1 holds the current sample for the next frame
+        eval.add_output(T::previous_output(1));
+        eval.add_output(signal);
+
+        self.insert_component_with_output(eval)
+    }
+
+    /// Rate of change of `signal` per second
+    ///
+    /// This is the change since the previous frame divided by the time between breadboard updates,
+    /// which is assumed to be ftd's fixed update interval of 1/40 seconds. The output is 0 on the first frame.
+    pub fn derivative(&self, signal: Line<BNumber>) -> Line<BNumber> {
+        self.derivative_of(signal)
+    }
+
+    /// Rate of change of each component of `signal` per second, see [`derivative`](Self::derivative)
+    pub fn derivative_vector(&self, signal: Line<BVector3>) -> Line<BVector3> {
+        self.derivative_of(signal)
+    }
+
+    /// PID controller driving `process` towards `setpoint`
+    ///
+    /// The error is `setpoint - process`. Like [`derivative`](Self::derivative) this assumes ftd's fixed update interval
+    /// of 1/40 seconds, and the derivative term is 0 on the first frame. The integral is not limited, so it can wind up
+    /// while the output is saturated.
+    pub fn pid(&self, setpoint: Line<BNumber>, process: Line<BNumber>, gains: PidGains) -> Line<BNumber> {
+        self.pid_of(setpoint, process, gains)
+    }
+
+    /// PID controller applied to each component of a vector, with the same gains for every axis, see [`pid`](Self::pid)
+    pub fn pid_vector(&self, setpoint: Line<BVector3>, process: Line<BVector3>, gains: PidGains) -> Line<BVector3> {
+        self.pid_of(setpoint, process, gains)
+    }
+
+    fn pid_of<T: FeedbackValue>(&self, setpoint: Line<T>, process: Line<T>, gains: PidGains) -> Line<T> {
+        let mut eval = Evaluator::default();
+        let [setpoint, process] = self.evaluator_inputs(&mut eval, [&setpoint, &process]);
+        let dt = EvaluatorExpression::Float(TICK_DELTA_TIME);
+
+        // output 1 holds the integral, output 2 the previous error, and output 3 is 0 only on the first frame
+        let error = setpoint - process;
+        let integral = T::previous_output(1) + error.clone() * dt.clone();
+        let derivative = EvaluatorExpression::if_else(
+            EvaluatorExpression::previous_output(3),
+            (error.clone() - T::previous_output(2)) / dt,
+            T::zero(),
+        );
+
+        eval.add_output(
+            error.clone() * EvaluatorExpression::Float(gains.kp.into())
+                + integral.clone() * EvaluatorExpression::Float(gains.ki.into())
+                + derivative * EvaluatorExpression::Float(gains.kd.into()),
+        );
+        eval.add_output(integral);
+        eval.add_output(error);
+        eval.add_output(EvaluatorExpression::Int(1));
+
+        self.insert_component_with_output(eval)
+    }
+
+    fn derivative_of<T: FeedbackValue>(&self, signal: Line<T>) -> Line<T> {
+        let mut eval = Evaluator::default();
+        let [signal] = self.evaluator_inputs(&mut eval, [&signal]);
+
+        // output 1 holds the previous sample, and output 2 is 0 only on the first frame
+        let change = signal.clone() - T::previous_output(1);
+        eval.add_output(EvaluatorExpression::if_else(
+            EvaluatorExpression::previous_output(2),
+            change / EvaluatorExpression::Float(TICK_DELTA_TIME),
+            T::zero(),
+        ));
+        eval.add_output(signal);
+        eval.add_output(EvaluatorExpression::Int(1));
+
+        self.insert_component_with_output(eval)
+    }
+}
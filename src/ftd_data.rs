@@ -1,24 +1,29 @@
+//! Data model for the binary block data stored in ftd blueprints, and its serialization
+//!
+//! This module is public because its types are part of the breadboard api: [`SectionData`] and [`DataEntry`] set
+//! component options the typed api does not model, [`BlueprintData`] is what breadboards are saved to and imported from,
+//! and without the `breadboard` feature this is the whole crate. Only the data model is public, the byte level
+//! serializer is private, so the format details can change without breaking users.
+
 use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io::{self, Write};
 
 use base64::prelude::*;
+use base64::write::EncoderWriter;
 use uuid::Uuid;
 
 /// Ftd's uuid's have a different notation, so this converts a uuid parsed from ftd's notation to a regular uuid
 pub fn ftd_uuid_to_uuid(uuid: Uuid) -> Uuid {
     let mut bytes = uuid.into_bytes();
 
-    let mut swap = |a, b| {
-        let tmp = bytes[a];
-        bytes[a] = bytes[b];
-        bytes[b] = tmp;
-    };
-
-    swap(0, 3);
-    swap(1, 2);
+    bytes.swap(0, 3);
+    bytes.swap(1, 2);
 
-    swap(4, 5);
+    bytes.swap(4, 5);
 
-    swap(6, 7);
+    bytes.swap(6, 7);
 
     Uuid::from_bytes(bytes)
 }
@@ -34,45 +39,138 @@ impl BlueprintData {
         self.data.insert(index, data);
     }
 
-    pub fn serialize(&self) -> Vec<u8> {
+    pub fn block_data(&self, index: BlockIndex) -> Option<&BlockData> {
+        self.data.get(&index)
+    }
+
+    /// Iterates over all blocks and their indices, in no particular order
+    pub fn blocks(&self) -> impl Iterator<Item = (BlockIndex, &BlockData)> {
+        self.data.iter().map(|(index, block)| (*index, block))
+    }
+
+    /// Serializes every block, fails if a block has more than [`MAX_SECTIONS`] sections
+    pub fn serialize(&self) -> Result<Vec<u8>, TooManySections> {
         let mut serializer = Serializer::default();
 
         for (block_index, block) in self.data.iter() {
-            // FIXME: don't allocate for every block, just easier to do now to get length before inserting into main buffer
-            let mut block_data_serializer = Serializer::default();
-            let block_serialize_info = block.serialize_to(&mut block_data_serializer);
-
-            serializer.push_u24(block_index.0);
-            serializer.push_u16(block_serialize_info.header_len.try_into().unwrap());
-
-            // this bytes are unused
-            serializer.push_u16(0);
-
-            // push body length, ftd has wierd way of storing data body length
-            let mut body_length = block_serialize_info.data_length;
-            loop {
-                if body_length >= u16::MAX as usize {
-                    serializer.push_u16(u16::MAX);
-                    body_length -= u16::MAX as usize;
-                } else {
-                    serializer.push_u16(body_length.try_into().unwrap());
-                    break;
-                }
-            }
+            Self::serialize_block(*block_index, block, &mut serializer)?;
+        }
+
+        Ok(serializer.into_inner())
+    }
 
-            serializer.push_bytes(block_data_serializer.as_slice());
+    /// Writes the serialized data to `writer` one block at a time, instead of building all of it in memory first
+    ///
+    /// A block with more than [`MAX_SECTIONS`] sections is an [`InvalidInput`](io::ErrorKind::InvalidInput) error,
+    /// blocks before it have already been written when it is returned.
+    pub fn serialize_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for (block_index, block) in self.data.iter() {
+            let mut serializer = Serializer::default();
+            Self::serialize_block(*block_index, block, &mut serializer)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+            writer.write_all(serializer.as_slice())?;
+        }
+
+        Ok(())
+    }
+
+    /// Serializes the data like [`serialize`](Self::serialize), but as an annotated hex dump for debugging the format
+    ///
+    /// Each line is the offset of a field, its bytes in hex and what the field is, such as a block index, the header
+    /// and body lengths of a block, the id and body offset of a section or the id and value of an entry.
+    /// Fields longer than 16 bytes continue on following lines without a label.
+    pub fn hexdump(&self) -> Result<String, TooManySections> {
+        let mut serializer = Serializer::annotated();
+
+        for (block_index, block) in self.data.iter() {
+            Self::serialize_block(*block_index, block, &mut serializer)?;
         }
 
-        serializer.into_inner()
+        Ok(serializer.hexdump())
+    }
+
+    fn serialize_block(block_index: BlockIndex, block: &BlockData, serializer: &mut Serializer) -> Result<(), TooManySections> {
+        // FIXME: don't allocate for every block, just easier to do now to get length before inserting into main buffer
+        let mut block_data_serializer = serializer.nested();
+        let block_serialize_info = block.serialize_to(&mut block_data_serializer);
+
+        let header_len = u16::try_from(block_serialize_info.header_len).map_err(|_| TooManySections {
+            block_index,
+            count: block.sections.len(),
+        })?;
+
+        serializer.label(|| format!("block {}: block index", block_index.0));
+        serializer.push_u24(block_index.0);
+        serializer.label(|| format!("header length {header_len}"));
+        serializer.push_u16(header_len);
+
+        // this bytes are unused
+        serializer.label(|| String::from("unused"));
+        serializer.push_u16(0);
+
+        // push body length, ftd has wierd way of storing data body length
+        serializer.label(|| format!("body length {}", block_serialize_info.data_length));
+        let mut body_length = block_serialize_info.data_length;
+        loop {
+            if body_length >= u16::MAX as usize {
+                serializer.push_u16(u16::MAX);
+                body_length -= u16::MAX as usize;
+            } else {
+                serializer.push_u16(body_length.try_into().unwrap());
+                break;
+            }
+        }
+
+        serializer.append(block_data_serializer);
+
+        Ok(())
     }
 
     /// Creates a serialized base64 string which should be inserted in the block data section of an ftd blueprint file
-    pub fn serialize_to_bp_data_string(&self) -> String {
-        let data = self.serialize();
-        BASE64_STANDARD.encode(&data)
+    pub fn serialize_to_bp_data_string(&self) -> Result<String, TooManySections> {
+        let data = self.serialize()?;
+        Ok(BASE64_STANDARD.encode(&data))
+    }
+
+    /// Writes the same base64 string as [`serialize_to_bp_data_string`](Self::serialize_to_bp_data_string) to `writer`
+    pub fn write_bp_data_string<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut encoder = EncoderWriter::new(writer, &BASE64_STANDARD);
+        self.serialize_to(&mut encoder)?;
+        encoder.finish()?;
+
+        Ok(())
+    }
+}
+
+/// Length of the header of each section in a block, a u24 section id followed by a weird u32 offset into the block body
+const SECTION_HEADER_LEN: usize = 3 + 4;
+
+/// Maximum number of sections in one block
+///
+/// The length of all section headers of a block is stored in a u16, so only this many headers fit.
+pub const MAX_SECTIONS: usize = u16::MAX as usize / SECTION_HEADER_LEN;
+
+/// A block had more than [`MAX_SECTIONS`] sections, so its header length does not fit in a u16
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TooManySections {
+    pub block_index: BlockIndex,
+    pub count: usize,
+}
+
+impl Display for TooManySections {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "block {} has {} sections, but at most {MAX_SECTIONS} can be serialized",
+            self.block_index.0,
+            self.count,
+        )
     }
 }
 
+impl Error for TooManySections {}
+
 #[derive(Debug, Default)]
 pub struct BlockData {
     sections: HashMap<SectionId, SectionData>,
@@ -88,28 +186,53 @@ impl BlockData {
         self.sections.insert(id, data);
     }
 
+    pub fn section_data(&self, id: SectionId) -> Option<&SectionData> {
+        self.sections.get(&id)
+    }
+
+    /// Serializes this block as the block at `index`, the same way [`BlueprintData::serialize`] serializes each of its blocks
+    ///
+    /// A blueprint's block data is every block serialized one after another, so this can be appended to the
+    /// decoded block data of an existing blueprint to add this block to it.
+    pub fn serialize(&self, index: BlockIndex) -> Result<Vec<u8>, TooManySections> {
+        let mut serializer = Serializer::default();
+        BlueprintData::serialize_block(index, self, &mut serializer)?;
+        Ok(serializer.into_inner())
+    }
+
+    /// Creates a base64 string containing only this block at `index`
+    ///
+    /// The string must be placed in the `BlockData` field of an ftd blueprint file, replacing its other blocks.
+    /// To keep the other blocks, decode the field and append [`serialize`](Self::serialize) instead.
+    pub fn to_bp_data_string(&self, index: BlockIndex) -> Result<String, TooManySections> {
+        Ok(BASE64_STANDARD.encode(self.serialize(index)?))
+    }
+
     fn serialize_to(&self, serializer: &mut Serializer) -> BlockDataSerializeInfo {
         // serialize data body seperate from headers so we know offsets from start of data easily
-        let mut data_body = Serializer::default();
+        let mut data_body = serializer.nested();
 
         for (section_id, section) in self.sections.iter() {
             let section_start_offset = data_body.len();
+            data_body.label(|| format!("section {}: body", section_id.0));
             section.serialize_to(&mut data_body);
 
+            serializer.label(|| format!("section {}: header, body offset {section_start_offset}", section_id.0));
             serializer.push_u24(section_id.0);
             serializer.push_wierd_u32(section_start_offset.try_into().unwrap());
         }
 
-        serializer.push_bytes(data_body.as_slice());
+        let data_length = data_body.len();
+        serializer.append(data_body);
 
         BlockDataSerializeInfo {
-            header_len: 7 * self.sections.len(),
-            data_length: data_body.len(),
+            header_len: SECTION_HEADER_LEN * self.sections.len(),
+            data_length,
         }
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct SectionData {
     entries: HashMap<DataEntryId, DataEntry>,
 }
@@ -124,6 +247,26 @@ impl SectionData {
         self
     }
 
+    pub fn entry(&self, id: DataEntryId) -> Option<&DataEntry> {
+        self.entries.get(&id)
+    }
+
+    /// Iterates over all entries and their ids, in no particular order
+    pub fn entries(&self) -> impl Iterator<Item = (DataEntryId, &DataEntry)> {
+        self.entries.iter().map(|(id, entry)| (*id, entry))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds all entries from `other`, replacing any entries with the same id
+    pub fn merge(&mut self, other: &SectionData) {
+        for (id, entry) in other.entries.iter() {
+            self.entries.insert(*id, entry.clone());
+        }
+    }
+
     fn serialize_to(&self, serializer: &mut Serializer) {
         for (entry_id, entry_data) in self.entries.iter() {
             entry_data.serialize_to(*entry_id, serializer);
@@ -131,7 +274,12 @@ impl SectionData {
     }
 }
 
-#[derive(Debug)]
+/// A value stored in a section, all values are stored little endian
+///
+/// Ftd does not store the type of an entry, so the component reading it decides how its bytes are interpreted.
+/// Component options are f32 or u32 (4 bytes), `I64` and `F64` (8 bytes) exist in the format
+/// but none of the components this crate emits read them.
+#[derive(Debug, Clone)]
 pub enum DataEntry {
     Bool(bool),
     U32(u32),
@@ -140,13 +288,26 @@ pub enum DataEntry {
     I64(i64),
     F64(f64),
     Vector2(Vector2),
+    /// Three f32s with a length of 12, laid out like `Vector2` with one more f32
+    ///
+    /// This layout has not been compared against a blueprint saved by ftd, and none of the components this crate
+    /// emits are known to have vector options, so nothing writes this.
+    Vector3(Vector3),
     Uuid(Uuid),
     Bytes(Vec<u8>),
     String(String),
 }
 
 impl DataEntry {
+    /// The bytes this entry is saved as, starting with `entry_id`
+    pub(crate) fn to_bytes(&self, entry_id: DataEntryId) -> Vec<u8> {
+        let mut serializer = Serializer::default();
+        self.serialize_to(entry_id, &mut serializer);
+        serializer.data
+    }
+
     fn serialize_to(&self, entry_id: u16, serializer: &mut Serializer) {
+        serializer.label(|| format!("entry {entry_id}: {self:?}"));
         serializer.push_u16(entry_id);
 
         match self {
@@ -179,6 +340,12 @@ impl DataEntry {
                 serializer.push_f32(val.x);
                 serializer.push_f32(val.y);
             },
+            Self::Vector3(val) => {
+                serializer.push_u8(12);
+                serializer.push_f32(val.x);
+                serializer.push_f32(val.y);
+                serializer.push_f32(val.z);
+            },
             Self::Uuid(val) => {
                 serializer.push_u8(16);
                 serializer.push_bytes(val.as_bytes());
@@ -199,11 +366,12 @@ impl DataEntry {
             serializer.push_bytes(&data[..write_amount]);
 
             data = &data[write_amount..]; 
-            if data.len() == 0 {
+            if data.is_empty() {
                 break;
             }
 
             // more data to write, ftd represents this with another consecutive element with the same id
+            serializer.label(|| format!("entry {entry_id}: continued"));
             serializer.push_u16(entry_id);
         }
     }
@@ -224,17 +392,60 @@ impl Vector2 {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vector3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Vector3 {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Vector3 {
+            x,
+            y,
+            z,
+        }
+    }
+}
+
+/// Largest value which fits in the 3 bytes ftd stores block indices and section ids in
+pub const MAX_U24: u32 = (1 << 24) - 1;
+
+/// A block index or section id was larger than [`MAX_U24`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdOutOfRange {
+    pub value: u32,
+}
+
+impl Display for IdOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is too large to fit in 3 bytes, the maximum is {MAX_U24}", self.value)
+    }
+}
+
+impl Error for IdOutOfRange {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct BlockIndex(u32);
 
 impl BlockIndex {
     pub fn new(n: u32) -> Self {
-        if n >= 1 << 24 {
+        if n > MAX_U24 {
             panic!("block index to big to fit in 3 bytes");
         }
 
         BlockIndex(n)
     }
+
+    /// Like [`new`](Self::new), but returns an error instead of panicking if `n` is larger than [`MAX_U24`]
+    pub fn try_new(n: u32) -> Result<Self, IdOutOfRange> {
+        if n > MAX_U24 {
+            return Err(IdOutOfRange { value: n });
+        }
+
+        Ok(BlockIndex(n))
+    }
 }
 
 impl From<u32> for BlockIndex {
@@ -248,12 +459,21 @@ pub struct SectionId(u32);
 
 impl SectionId {
     pub fn new(n: u32) -> Self {
-        if n >= 1 << 24 {
+        if n > MAX_U24 {
             panic!("section id to big to fit in 3 bytes");
         }
 
         SectionId(n)
     }
+
+    /// Like [`new`](Self::new), but returns an error instead of panicking if `n` is larger than [`MAX_U24`]
+    pub fn try_new(n: u32) -> Result<Self, IdOutOfRange> {
+        if n > MAX_U24 {
+            return Err(IdOutOfRange { value: n });
+        }
+
+        Ok(SectionId(n))
+    }
 }
 
 impl From<u32> for SectionId {
@@ -264,12 +484,79 @@ impl From<u32> for SectionId {
 
 pub type DataEntryId = u16;
 
+/// Number of bytes on each line of a hex dump
+const HEXDUMP_LINE_LEN: usize = 16;
+
 #[derive(Default)]
 struct Serializer {
     data: Vec<u8>,
+    /// Offset and description of the start of each field, only collected by serializers building a hex dump
+    labels: Option<Vec<(usize, String)>>,
 }
 
 impl Serializer {
+    fn annotated() -> Self {
+        Serializer {
+            data: Vec::new(),
+            labels: Some(Vec::new()),
+        }
+    }
+
+    /// Creates an empty serializer which collects labels only if this one does, to be [`append`](Self::append)ed later
+    fn nested(&self) -> Self {
+        if self.labels.is_some() {
+            Self::annotated()
+        } else {
+            Self::default()
+        }
+    }
+
+    /// Labels the field starting at the current end of the data, `label` is only called when building a hex dump
+    fn label(&mut self, label: impl FnOnce() -> String) {
+        if let Some(labels) = &mut self.labels {
+            labels.push((self.data.len(), label()));
+        }
+    }
+
+    fn append(&mut self, other: Serializer) {
+        let offset = self.data.len();
+        if let (Some(labels), Some(other_labels)) = (&mut self.labels, other.labels) {
+            labels.extend(other_labels.into_iter().map(|(start, label)| (offset + start, label)));
+        }
+
+        self.data.extend_from_slice(&other.data);
+    }
+
+    fn hexdump(&self) -> String {
+        let labels = self.labels.as_deref().unwrap_or_default();
+        let mut out = String::new();
+
+        for (i, (start, label)) in labels.iter().enumerate() {
+            let end = labels.get(i + 1).map_or(self.data.len(), |(next_start, _)| *next_start);
+            let bytes = &self.data[*start..end];
+
+            let mut line_start = *start;
+            let mut chunks = bytes.chunks(HEXDUMP_LINE_LEN);
+            let first_chunk = chunks.next().unwrap_or_default();
+            Self::write_hexdump_line(&mut out, line_start, first_chunk, label);
+
+            for chunk in chunks {
+                line_start += HEXDUMP_LINE_LEN;
+                Self::write_hexdump_line(&mut out, line_start, chunk, "");
+            }
+        }
+
+        out
+    }
+
+    fn write_hexdump_line(out: &mut String, offset: usize, bytes: &[u8], label: &str) {
+        use std::fmt::Write;
+
+        let hex = bytes.iter().map(|byte| format!("{byte:02x}")).collect::<Vec<_>>().join(" ");
+        let line = format!("{offset:08x}  {hex:<width$}  {label}", width = HEXDUMP_LINE_LEN * 3 - 1);
+        writeln!(out, "{}", line.trim_end()).unwrap();
+    }
+
     fn push_u8(&mut self, n: u8) {
         self.data.push(n);
     }
@@ -310,4 +597,214 @@ impl Serializer {
     fn into_inner(self) -> Vec<u8> {
         self.data
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Block with one section containing a single f32 entry
+    fn single_entry_block() -> BlockData {
+        let mut block = BlockData::default();
+        block.add_section_data(5.into(), SectionData::default().with_entry(1, DataEntry::F32(1.0)));
+        block
+    }
+
+    #[test]
+    fn block_layout() {
+        let mut data = BlueprintData::default();
+        data.add_block_data(2.into(), single_entry_block());
+
+        #[rustfmt::skip]
+        let expected = [
+            // block index
+            2, 0, 0,
+            // header length
+            7, 0,
+            // unused
+            0, 0,
+            // body length
+            7, 0,
+            // section header: section id, then weird u32 offset of 0
+            5, 0, 0, 0, 0, 0, 0,
+            // section body: entry id, entry length, f32 1.0
+            1, 0, 4, 0x00, 0x00, 0x80, 0x3f,
+        ];
+
+        assert_eq!(data.serialize().unwrap(), expected);
+    }
+
+    #[test]
+    fn header_length_counts_every_section() {
+        let mut block = single_entry_block();
+        block.add_section_data(6.into(), SectionData::default().with_entry(1, DataEntry::U32(3)));
+        block.add_section_data(7.into(), SectionData::default());
+
+        let mut data = BlueprintData::default();
+        data.add_block_data(0.into(), block);
+        let bytes = data.serialize().unwrap();
+
+        let header_len = u16::from_le_bytes([bytes[3], bytes[4]]) as usize;
+        let body_len = u16::from_le_bytes([bytes[7], bytes[8]]) as usize;
+
+        assert_eq!(header_len, 3 * SECTION_HEADER_LEN);
+        assert_eq!(body_len, 2 * 7);
+        assert_eq!(bytes.len(), 9 + header_len + body_len);
+    }
+
+    /// Block with one section whose serialized body is exactly `body_len` bytes, which must be at least 18
+    fn block_with_body_len(body_len: usize) -> BlockData {
+        // f32 entries take 7 bytes and bool entries 4, so a mix of at most 3 f32 entries makes up any length
+        let f32_count = (0..4).find(|n| (body_len - 7 * n).is_multiple_of(4)).unwrap();
+        let bool_count = (body_len - 7 * f32_count) / 4;
+
+        let mut section = SectionData::default();
+        for id in 0..f32_count + bool_count {
+            let entry = if id < f32_count { DataEntry::F32(0.0) } else { DataEntry::Bool(true) };
+            section.add_entry(id.try_into().unwrap(), entry);
+        }
+
+        let mut block = BlockData::default();
+        block.add_section_data(1.into(), section);
+        block
+    }
+
+    #[test]
+    fn body_length_encoding() {
+        let max = u16::MAX as usize;
+
+        for (body_len, expected_prefix) in [
+            (max - 1, vec![0xfe, 0xff]),
+            // a length of exactly u16::MAX is a marker followed by a 0
+            (max, vec![0xff, 0xff, 0, 0]),
+            (max + 10, vec![0xff, 0xff, 10, 0]),
+            (2 * max + 1, vec![0xff, 0xff, 0xff, 0xff, 1, 0]),
+        ] {
+            let bytes = block_with_body_len(body_len).serialize(0.into()).unwrap();
+
+            let body_start = 7 + expected_prefix.len() + SECTION_HEADER_LEN;
+            assert_eq!(bytes[7..7 + expected_prefix.len()], expected_prefix, "body length {body_len}");
+            assert_eq!(bytes.len() - body_start, body_len);
+        }
+    }
+
+    #[test]
+    fn vector3_entry_layout() {
+        let mut block = BlockData::default();
+        block.add_section_data(5.into(), SectionData::default().with_entry(1, DataEntry::Vector3(Vector3::new(1.0, -2.0, 0.5))));
+
+        // written by hand from the Vector2 layout, there is no blueprint saved by ftd with a vector entry to compare against
+        #[rustfmt::skip]
+        let expected_body = [
+            // entry id, entry length, then x, y and z as little endian f32s
+            1, 0, 12,
+            0x00, 0x00, 0x80, 0x3f,
+            0x00, 0x00, 0x00, 0xc0,
+            0x00, 0x00, 0x00, 0x3f,
+        ];
+
+        let serialized = block.serialize(0.into()).unwrap();
+        assert_eq!(serialized[serialized.len() - expected_body.len()..], expected_body);
+    }
+
+    #[test]
+    fn wide_entry_layout() {
+        let section = SectionData::default()
+            .with_entry(3, DataEntry::I64(-2))
+            .with_entry(4, DataEntry::F64(1.5));
+
+        let mut i64_bytes = Serializer::default();
+        section.entry(3).unwrap().serialize_to(3, &mut i64_bytes);
+        let mut f64_bytes = Serializer::default();
+        section.entry(4).unwrap().serialize_to(4, &mut f64_bytes);
+
+        #[rustfmt::skip]
+        let expected_i64 = [
+            // entry id, entry length, then -2 as a little endian i64
+            3, 0, 8,
+            0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        ];
+        #[rustfmt::skip]
+        let expected_f64 = [
+            // entry id, entry length, then 1.5 as a little endian f64
+            4, 0, 8,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf8, 0x3f,
+        ];
+
+        assert_eq!(i64_bytes.as_slice(), expected_i64);
+        assert_eq!(f64_bytes.as_slice(), expected_f64);
+    }
+
+    #[test]
+    fn hexdump_labels_fields() {
+        let mut data = BlueprintData::default();
+        data.add_block_data(2.into(), single_entry_block());
+
+        let expected = "\
+00000000  02 00 00                                         block 2: block index
+00000003  07 00                                            header length 7
+00000005  00 00                                            unused
+00000007  07 00                                            body length 7
+00000009  05 00 00 00 00 00 00                             section 5: header, body offset 0
+00000010                                                   section 5: body
+00000010  01 00 04 00 00 80 3f                             entry 1: F32(1.0)
+";
+        assert_eq!(data.hexdump().unwrap(), expected);
+    }
+
+    #[test]
+    fn id_range() {
+        assert_eq!(SectionId::try_new(MAX_U24), Ok(SectionId::new(MAX_U24)));
+        assert_eq!(SectionId::try_new(MAX_U24 + 1), Err(IdOutOfRange { value: MAX_U24 + 1 }));
+        assert_eq!(BlockIndex::try_new(MAX_U24), Ok(BlockIndex::new(MAX_U24)));
+        assert_eq!(BlockIndex::try_new(u32::MAX), Err(IdOutOfRange { value: u32::MAX }));
+    }
+
+    #[test]
+    fn writer_matches_serialize() {
+        let mut data = BlueprintData::default();
+        data.add_block_data(0.into(), single_entry_block());
+
+        let mut written = Vec::new();
+        data.serialize_to(&mut written).unwrap();
+
+        assert_eq!(written, data.serialize().unwrap());
+    }
+
+    #[test]
+    fn single_block_matches_blueprint() {
+        let mut data = BlueprintData::default();
+        data.add_block_data(3.into(), single_entry_block());
+
+        let block = single_entry_block();
+        assert_eq!(block.serialize(3.into()).unwrap(), data.serialize().unwrap());
+        assert_eq!(block.to_bp_data_string(3.into()).unwrap(), data.serialize_to_bp_data_string().unwrap());
+    }
+
+    /// Block with `count` empty sections
+    fn block_with_sections(count: usize) -> BlockData {
+        let mut block = BlockData::default();
+        for id in 0..count {
+            block.add_section_data(SectionId::new(id.try_into().unwrap()), SectionData::default());
+        }
+        block
+    }
+
+    #[test]
+    fn section_count_limit() {
+        let bytes = block_with_sections(MAX_SECTIONS).serialize(0.into()).unwrap();
+        let header_len = u16::from_le_bytes([bytes[3], bytes[4]]) as usize;
+        assert_eq!(header_len, MAX_SECTIONS * SECTION_HEADER_LEN);
+
+        let mut data = BlueprintData::default();
+        data.add_block_data(1.into(), block_with_sections(MAX_SECTIONS + 1));
+
+        let expected = TooManySections {
+            block_index: 1.into(),
+            count: MAX_SECTIONS + 1,
+        };
+        assert_eq!(data.serialize(), Err(expected));
+        assert_eq!(data.hexdump(), Err(expected));
+        assert_eq!(data.serialize_to(&mut Vec::new()).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -1,16 +1,30 @@
+//! Generates ftd breadboards and saves them as prefab blueprints
+//!
+//! # Features
+//!
+//! - `breadboard` (default): the [`Breadboard`] building api and the [`prelude`].
+//!   Without it only the [`ftd_data`] and [`save_location`] modules are built, which only depend on `base64` and `uuid`.
+
+#[cfg(feature = "breadboard")]
 mod breadboard;
-mod ftd_data;
+pub mod ftd_data;
+pub mod save_location;
+#[cfg(feature = "breadboard")]
 pub mod prelude;
 
+#[cfg(feature = "breadboard")]
 use std::{path::PathBuf, str::FromStr};
 
-pub use breadboard::{Breadboard, SwitchOptions};
+#[cfg(feature = "breadboard")]
+pub use breadboard::{Breadboard, BreadboardError, EvaluatorExpression, ExpressionTypeError, ImportError, ParseError, SimValue, Simulation, SimulationError, SwitchOptions, ValidationError, BREADBOARD_MATERIAL_COST, COMPONENT_VALUE_LIMIT, MAX_COMPONENTS, MAX_EVALUATOR_EXPRESSION_LENGTH, MAX_MOVING_AVERAGE_WINDOW, MAX_MULTIPLY_INPUTS, MULTIPLIER_LIMIT, PERSISTENT_INDEX_UNSET, TICK_DELTA_TIME, ValueType};
 
+#[cfg(feature = "breadboard")]
 fn find_ftd_folder() -> PathBuf {
-    // FIXME: this is just hardcoded for me for now
+    // FIXME: this is just hardcoded for me for now, it should let the user pick one of save_location::find_player_profiles
     PathBuf::from_str("/home/jack/From The Depths/Player Profiles/DeltaForce").unwrap()
 }
 
+#[cfg(feature = "breadboard")]
 fn find_prefabs_folder() -> PathBuf {
     find_ftd_folder().join("PrefabsVersion2")
 }
\ No newline at end of file
@@ -1,11 +1,56 @@
+//! Everything needed to build a breadboard with one import
+//!
+//! ```
+//! use bakery::prelude::*;
+//!
+//! let breadboard = Breadboard::new();
+//!
+//! let target = breadboard.target_info();
+//! let altitude = breadboard.altitude(AltitudeOutputType::SeaLevel);
+//! let speed = breadboard.speed(SpeedOutputType::ForwardsMagnitude);
+//!
+//! let weighted = &(&altitude + &speed) * 0.5;
+//! let output = b_if(&target.present, &weighted, &target.distance);
+//! let _ = breadboard.switch(output, target.present, SwitchOptions::default());
+//!
+//! let mut blueprint = Vec::new();
+//! breadboard.write_blueprint(&mut blueprint).unwrap();
+//! ```
+
 pub use crate::breadboard::{
     Breadboard,
+    MergedBreadboard,
+    BreadboardError,
+    ImportError,
+    ParseError,
+    Simulation,
+    SimulationError,
+    ValidationError,
+    SimValue,
     Line,
+    LineInner,
+    LineValue,
+    InputGroup,
     BNumber,
     BQuaternion,
     BVector3,
     BString,
+    EvaluatorExpression,
+    ExpressionTypeError,
+    ValueType,
     TargetInfoOutputs,
+    AltitudeOutputType,
+    SpeedOutputType,
+    VelocityOutputType,
     SwitchOptions,
+    StateTransition,
+    PidGains,
+    EvaluatorBuilder,
+    EvaluatorOutput,
+    EvaluatorOutputGroup,
+    BuiltEvaluator,
+    FeedbackValue,
+    TICK_DELTA_TIME,
     b_if,
-};
\ No newline at end of file
+    vec3,
+};
@@ -0,0 +1,120 @@
+//! Finding ftd player profiles in the default save locations
+//!
+//! Ftd saves into a `From The Depths` folder in the user's documents folder, with one folder per profile
+//! in its `Player Profiles` folder. Where the documents folder is depends on the os and on how the game is run:
+//!
+//! - windows: `%USERPROFILE%\Documents`, or `%USERPROFILE%\OneDrive\Documents` if documents are synced to onedrive
+//! - linux, native: the home folder, or `~/Documents` on some setups
+//! - linux, proton: `Documents` of the `steamuser` in the game's proton prefix, under either steam install location
+//! - macos: `~/Documents`, or the home folder on some setups
+//!
+//! Save folders moved somewhere else are not found.
+
+use std::env;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Steam app id of ftd, used to find its proton prefix
+const FTD_STEAM_APP_ID: u32 = 268650;
+
+/// Error returned when no player profile is found in any default save location
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoProfilesFound {
+    /// `Player Profiles` folders which were searched
+    pub searched: Vec<PathBuf>,
+}
+
+impl Display for NoProfilesFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no ftd player profiles found, searched: ")?;
+
+        for (i, path) in self.searched.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{}", path.display())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for NoProfilesFound {}
+
+/// Returns the `Player Profiles` folders ftd may use on this os, whether or not they exist
+pub fn default_profiles_folders() -> Vec<PathBuf> {
+    let Some(home) = home_folder() else {
+        return Vec::new();
+    };
+
+    let documents_folders = if cfg!(windows) {
+        vec![home.join("Documents"), home.join("OneDrive").join("Documents")]
+    } else if cfg!(target_os = "macos") {
+        vec![home.join("Documents"), home.clone()]
+    } else {
+        let mut folders = vec![home.clone(), home.join("Documents")];
+
+        for steam_folder in [home.join(".steam").join("steam"), home.join(".local").join("share").join("Steam")] {
+            folders.push(
+                steam_folder.join("steamapps")
+                    .join("compatdata")
+                    .join(FTD_STEAM_APP_ID.to_string())
+                    .join("pfx")
+                    .join("drive_c")
+                    .join("users")
+                    .join("steamuser")
+                    .join("Documents"),
+            );
+        }
+
+        folders
+    };
+
+    documents_folders.into_iter()
+        .map(|folder| folder.join("From The Depths").join("Player Profiles"))
+        .collect()
+}
+
+/// Returns every player profile folder in the default save locations, see the [module docs](self)
+///
+/// The same profile may be found more than once if save locations are symlinked to each other.
+pub fn find_player_profiles() -> Result<Vec<PathBuf>, NoProfilesFound> {
+    let searched = default_profiles_folders();
+
+    let profiles: Vec<PathBuf> = searched.iter()
+        .flat_map(|folder| profiles_in(folder))
+        .collect();
+
+    if profiles.is_empty() {
+        Err(NoProfilesFound {
+            searched,
+        })
+    } else {
+        Ok(profiles)
+    }
+}
+
+/// Returns the folders in a `Player Profiles` folder, or nothing if it can't be read
+fn profiles_in(folder: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(folder) else {
+        return Vec::new();
+    };
+
+    let mut profiles: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    // read_dir order is not specified, so profiles are sorted to keep the result stable
+    profiles.sort();
+
+    profiles
+}
+
+fn home_folder() -> Option<PathBuf> {
+    let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    env::var_os(var).map(PathBuf::from)
+}